@@ -1,3 +1,5 @@
+mod trickle;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("dachannel: {0}")]
@@ -8,16 +10,27 @@ pub enum Error {
 
     #[error("malformed body")]
     MalformedBody,
+
+    #[error("missing or malformed Location header")]
+    MalformedLocation,
+
+    #[error("rejected with status {status}: {body}")]
+    Rejected {
+        status: reqwest::StatusCode,
+        body: String,
+    },
 }
 
 pub struct ConnectOptions {
     headers: reqwest::header::HeaderMap,
+    trickle_ice: bool,
 }
 
 impl ConnectOptions {
     pub fn new() -> Self {
         Self {
             headers: reqwest::header::HeaderMap::new(),
+            trickle_ice: false,
         }
     }
 
@@ -30,13 +43,25 @@ impl ConnectOptions {
         self
     }
 
+    /// Send the offer immediately instead of waiting for ICE candidates to be gathered, and exchange candidates with the
+    /// server afterwards. The server must also have trickle ICE enabled.
+    ///
+    /// With trickle ICE, connecting completes once the connection is connected.
+    pub fn trickle_ice(mut self, trickle_ice: bool) -> Self {
+        self.trickle_ice = trickle_ice;
+        self
+    }
+
     /// Connect to a dachannel server.
+    ///
+    /// Once the connection is closed or fails, the server's session for it is deleted so the server closes its side
+    /// too.
     pub async fn connect(
         self,
         cb: dachannel::ConnectionBuilder,
         url: &str,
     ) -> Result<dachannel::Connection, Error> {
-        let conn = cb.build();
+        let mut conn = cb.build();
 
         conn.set_local_description(dachannel::SdpType::Offer)
             .await?;
@@ -46,10 +71,25 @@ impl ConnectOptions {
         let res = client
             .post(url)
             .body(offer_sdp)
-            .headers(self.headers)
+            .headers(self.headers.clone())
             .send()
-            .await?
-            .error_for_status()?;
+            .await?;
+        if !res.status().is_success() {
+            return Err(Error::Rejected {
+                status: res.status(),
+                body: res.text().await.unwrap_or_default(),
+            });
+        }
+        let session_url = res
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .map(|v| {
+                v.to_str()
+                    .ok()
+                    .and_then(|location| res.url().join(location).ok())
+                    .ok_or(Error::MalformedLocation)
+            })
+            .transpose()?;
         let answer_sdp =
             String::from_utf8(res.bytes().await?.to_vec()).map_err(|_| Error::MalformedBody)?;
 
@@ -59,28 +99,146 @@ impl ConnectOptions {
         })
         .await?;
 
+        if self.trickle_ice {
+            let session_url = session_url.as_ref().ok_or(Error::MalformedLocation)?;
+            trickle::run(&mut conn, &client, session_url, &self.headers).await?;
+        }
+
+        if let Some(session_url) = session_url {
+            spawn(delete_session_on_close(
+                conn.watch_state(),
+                client,
+                session_url,
+                self.headers,
+            ));
+        }
+
         Ok(conn)
     }
 }
 
+/// Wait for the connection to close or fail, then delete its session on the server.
+async fn delete_session_on_close(
+    mut connection_state: dachannel::Watcher<dachannel::PeerConnectionState>,
+    client: reqwest::Client,
+    session_url: reqwest::Url,
+    headers: reqwest::header::HeaderMap,
+) {
+    connection_state
+        .wait_for(|state| {
+            matches!(
+                state,
+                dachannel::PeerConnectionState::Failed | dachannel::PeerConnectionState::Closed
+            )
+        })
+        .await;
+
+    if let Err(e) = client
+        .delete(session_url)
+        .headers(headers)
+        .send()
+        .await
+        .and_then(|res| res.error_for_status())
+    {
+        log::warn!("failed to delete session: {e}");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn(fut: impl std::future::Future<Output = ()> + Send + 'static) {
+    tokio::spawn(fut);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn spawn(fut: impl std::future::Future<Output = ()> + 'static) {
+    wasm_bindgen_futures::spawn_local(fut);
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    /// Start a server on a local port with the options, returning its URL, its offers and a handle to shut it down.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn serve(
+        options: dachannel_server::ServeOptions,
+    ) -> (
+        String,
+        futures::channel::mpsc::Receiver<dachannel_server::Connecting>,
+        dachannel_server::ShutdownHandle,
+    ) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let shutdown_handle = options.shutdown_handle();
+        let (serve_fut, connecting_rx) = options.serve(listener);
+        tokio::spawn(async move {
+            serve_fut.await.unwrap();
+        });
+
+        (
+            format!("http://127.0.0.1:{}", local_addr.port()),
+            connecting_rx,
+            shutdown_handle,
+        )
+    }
+
     #[cfg(not(target_arch = "wasm32"))]
     #[tokio::test]
     pub async fn test_connect() {
         use futures::StreamExt as _;
 
-        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
-        let local_addr = listener.local_addr().unwrap();
+        let (url, mut connecting_rx, _shutdown_handle) =
+            serve(dachannel_server::ServeOptions::new()).await;
+
+        let client_jh = tokio::spawn(async move {
+            let config: dachannel::Configuration = Default::default();
+            let cb = dachannel::Connection::builder(config).unwrap();
+            let dc = cb
+                .create_data_channel(
+                    "test",
+                    dachannel::DataChannelOptions {
+                        negotiated: true,
+                        id: Some(1),
+                        ..Default::default()
+                    },
+                )
+                .unwrap();
 
-        let (serve_fut, mut connecting_rx) = dachannel_server::ServeOptions::new().serve(listener);
+            let _conn = ConnectOptions::new().connect(cb, &url).await.unwrap();
 
-        tokio::spawn(async move {
-            serve_fut.await.unwrap();
+            dc.send_binary(b"hello world").await.unwrap();
         });
 
+        let connecting = connecting_rx.next().await.unwrap();
+        let mut dc = connecting
+            .create_data_channel(
+                "test",
+                dachannel::DataChannelOptions {
+                    negotiated: true,
+                    id: Some(1),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let _conn = connecting.await.unwrap();
+        assert_eq!(
+            dc.recv().await.unwrap(),
+            dachannel::Message::Binary(b"hello world".to_vec())
+        );
+
+        client_jh.await.unwrap();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    pub async fn test_connect_trickle_ice() {
+        use futures::StreamExt as _;
+
+        let (url, mut connecting_rx, _shutdown_handle) =
+            serve(dachannel_server::ServeOptions::new().trickle_ice(true)).await;
+
         let client_jh = tokio::spawn(async move {
             let config: dachannel::Configuration = Default::default();
             let cb = dachannel::Connection::builder(config).unwrap();
@@ -95,12 +253,14 @@ mod test {
                 )
                 .unwrap();
 
-            let _conn = ConnectOptions::new()
-                .connect(cb, &format!("http://127.0.0.1:{}", local_addr.port()))
+            let conn = ConnectOptions::new()
+                .trickle_ice(true)
+                .connect(cb, &url)
                 .await
                 .unwrap();
+            assert_eq!(conn.state(), dachannel::PeerConnectionState::Connected);
 
-            dc.send(b"hello world").await.unwrap();
+            dc.send_binary(b"hello world").await.unwrap();
         });
 
         let connecting = connecting_rx.next().await.unwrap();
@@ -116,8 +276,62 @@ mod test {
             .unwrap();
 
         let _conn = connecting.await.unwrap();
-        assert_eq!(dc.recv().await.unwrap(), b"hello world");
+        assert_eq!(
+            dc.recv().await.unwrap(),
+            dachannel::Message::Binary(b"hello world".to_vec())
+        );
 
         client_jh.await.unwrap();
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    pub async fn test_close_deletes_session() {
+        use futures::StreamExt as _;
+
+        let (url, mut connecting_rx, _shutdown_handle) =
+            serve(dachannel_server::ServeOptions::new()).await;
+
+        let client_jh = tokio::spawn(async move {
+            let config: dachannel::Configuration = Default::default();
+            let cb = dachannel::Connection::builder(config).unwrap();
+            ConnectOptions::new().connect(cb, &url).await.unwrap()
+        });
+
+        let connecting = connecting_rx.next().await.unwrap();
+        let conn = connecting.await.unwrap();
+
+        let client_conn = client_jh.await.unwrap();
+        client_conn.close().unwrap();
+
+        conn.watch_state()
+            .wait_for(|state| *state == dachannel::PeerConnectionState::Closed)
+            .await;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    pub async fn test_connect_rejected() {
+        use futures::StreamExt as _;
+
+        let (url, mut connecting_rx, _shutdown_handle) =
+            serve(dachannel_server::ServeOptions::new()).await;
+
+        let client_jh = tokio::spawn(async move {
+            let config: dachannel::Configuration = Default::default();
+            let cb = dachannel::Connection::builder(config).unwrap();
+            ConnectOptions::new().connect(cb, &url).await
+        });
+
+        let connecting = connecting_rx.next().await.unwrap();
+        connecting.reject(axum::http::StatusCode::UNAUTHORIZED, "bad token");
+
+        match client_jh.await.unwrap() {
+            Err(Error::Rejected { status, body }) => {
+                assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+                assert_eq!(body, "bad token");
+            }
+            r => panic!("expected rejection, got {:?}", r.map(|_| ())),
+        }
+    }
 }
@@ -0,0 +1,311 @@
+use futures::future::FusedFuture as _;
+use futures::FutureExt as _;
+
+/// The media type of bodies carrying ICE candidates, from RFC 8840.
+const TRICKLE_ICE_SDPFRAG: &str = "application/trickle-ice-sdpfrag";
+
+/// The server's candidates, each with its mid if the server named one, and whether they are the last ones.
+type RemoteCandidates = (Vec<(String, Option<String>)>, bool);
+
+/// A pending request for the server's candidates. Requests are only Send natively.
+#[cfg(not(target_arch = "wasm32"))]
+type RemoteCandidatesFuture<'a> =
+    futures::future::BoxFuture<'a, Result<RemoteCandidates, crate::Error>>;
+#[cfg(target_arch = "wasm32")]
+type RemoteCandidatesFuture<'a> =
+    futures::future::LocalBoxFuture<'a, Result<RemoteCandidates, crate::Error>>;
+
+enum TrickleStep {
+    LocalCandidate(Option<String>),
+    LocalCandidatesGathered,
+    RemoteCandidates(Result<RemoteCandidates, crate::Error>),
+    ConnectionState(dachannel::PeerConnectionState),
+}
+
+/// Exchange candidates with the server through its session resource until the connection is connected.
+pub(crate) async fn run(
+    conn: &mut dachannel::Connection,
+    client: &reqwest::Client,
+    session_url: &reqwest::Url,
+    headers: &reqwest::header::HeaderMap,
+) -> Result<(), crate::Error> {
+    let header = conn
+        .local_description()?
+        .map(|description| sdpfrag_header(&description.sdp))
+        .unwrap_or_default();
+    let mut connection_state = conn.watch_state();
+    let mut ice_gathering_state = conn.watch_ice_gathering_state();
+    let mut local_done = false;
+    let mut remote_done = false;
+    let mut remote_candidates = futures::future::Fuse::<RemoteCandidatesFuture<'_>>::terminated();
+
+    loop {
+        if !remote_done && remote_candidates.is_terminated() {
+            let fut: RemoteCandidatesFuture<'_> =
+                Box::pin(get_candidates(client, session_url, headers));
+            remote_candidates = fut.fuse();
+        }
+
+        let step = futures::select! {
+            cand = async {
+                if local_done {
+                    futures::future::pending().await
+                } else {
+                    conn.next_ice_candidate().await
+                }
+            }.fuse() => TrickleStep::LocalCandidate(cand),
+            _ = async {
+                if local_done {
+                    futures::future::pending().await
+                } else {
+                    ice_gathering_state
+                        .wait_for(|state| *state == dachannel::IceGatheringState::Complete)
+                        .await
+                }
+            }.fuse() => TrickleStep::LocalCandidatesGathered,
+            result = remote_candidates => TrickleStep::RemoteCandidates(result),
+            state = connection_state.wait_for(|state| {
+                matches!(
+                    state,
+                    dachannel::PeerConnectionState::Connected
+                        | dachannel::PeerConnectionState::Failed
+                        | dachannel::PeerConnectionState::Closed
+                )
+            }).fuse() => TrickleStep::ConnectionState(state),
+        };
+
+        match step {
+            TrickleStep::LocalCandidate(Some(cand)) => {
+                if let Err(e) =
+                    patch_candidates(client, session_url, headers, &header, &[cand], false).await
+                {
+                    log::warn!("failed to send local candidate: {e}");
+                }
+            }
+            TrickleStep::LocalCandidate(None) | TrickleStep::LocalCandidatesGathered => {
+                let mut candidates = vec![];
+                while let Some(Some(cand)) = conn.next_ice_candidate().now_or_never() {
+                    candidates.push(cand);
+                }
+                if let Err(e) =
+                    patch_candidates(client, session_url, headers, &header, &candidates, true).await
+                {
+                    log::warn!("failed to send local candidates: {e}");
+                }
+                local_done = true;
+            }
+            TrickleStep::RemoteCandidates(Ok((candidates, end))) => {
+                for (cand, mid) in candidates {
+                    if let Err(e) = conn.add_ice_candidate(Some(&cand), mid.as_deref()).await {
+                        log::warn!("failed to add remote candidate {cand:?}: {e}");
+                    }
+                }
+                remote_done = end;
+            }
+            TrickleStep::RemoteCandidates(Err(e)) => {
+                log::warn!("failed to get remote candidates: {e}");
+                remote_done = true;
+            }
+            TrickleStep::ConnectionState(dachannel::PeerConnectionState::Failed) => {
+                return Err(dachannel::Error::ConnectionFailed.into());
+            }
+            TrickleStep::ConnectionState(dachannel::PeerConnectionState::Closed) => {
+                return Err(dachannel::Error::ConnectionClosed.into());
+            }
+            TrickleStep::ConnectionState(_) => {
+                return Ok(());
+            }
+        }
+    }
+}
+
+async fn patch_candidates(
+    client: &reqwest::Client,
+    session_url: &reqwest::Url,
+    headers: &reqwest::header::HeaderMap,
+    header: &str,
+    candidates: &[String],
+    end: bool,
+) -> Result<(), crate::Error> {
+    client
+        .patch(session_url.clone())
+        .headers(headers.clone())
+        .header(reqwest::header::CONTENT_TYPE, TRICKLE_ICE_SDPFRAG)
+        .body(format_sdpfrag(header, candidates, end))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Long-poll the session for the server's candidates.
+///
+/// Once the server has finished with the session, it no longer exists, which also ends the candidates.
+async fn get_candidates(
+    client: &reqwest::Client,
+    session_url: &reqwest::Url,
+    headers: &reqwest::header::HeaderMap,
+) -> Result<RemoteCandidates, crate::Error> {
+    let res = client
+        .get(session_url.clone())
+        .headers(headers.clone())
+        .send()
+        .await?;
+    if res.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok((vec![], true));
+    }
+    let body = res.error_for_status()?.text().await?;
+    Ok(parse_sdpfrag(&body))
+}
+
+/// Take the lines that RFC 8840 requires before the candidates of an `application/trickle-ice-sdpfrag` body from the
+/// local description: its ICE username fragment and password, and its first media section with that section's mid.
+fn sdpfrag_header(sdp: &str) -> String {
+    let mut ice_ufrag = None;
+    let mut ice_pwd = None;
+    let mut media = None;
+    let mut mid = None;
+    for line in sdp.lines().map(|line| line.trim_end()) {
+        if line.starts_with("m=") {
+            if media.is_some() {
+                break;
+            }
+            media = Some(line);
+        } else if line.starts_with("a=ice-ufrag:") {
+            ice_ufrag.get_or_insert(line);
+        } else if line.starts_with("a=ice-pwd:") {
+            ice_pwd.get_or_insert(line);
+        } else if line.starts_with("a=mid:") && media.is_some() {
+            mid.get_or_insert(line);
+        }
+    }
+
+    let mut header = String::new();
+    for line in [ice_ufrag, ice_pwd, media, mid].into_iter().flatten() {
+        header.push_str(line);
+        header.push_str("\r\n");
+    }
+    header
+}
+
+/// Format candidates as an `application/trickle-ice-sdpfrag` body, after the header from [`sdpfrag_header`].
+fn format_sdpfrag(header: &str, candidates: &[String], end: bool) -> String {
+    let mut body = header.to_string();
+    for cand in candidates {
+        body.push_str("a=");
+        body.push_str(cand.strip_prefix("a=").unwrap_or(cand));
+        body.push_str("\r\n");
+    }
+    if end {
+        body.push_str("a=end-of-candidates\r\n");
+    }
+    body
+}
+
+/// Parse candidates from an `application/trickle-ice-sdpfrag` body, each with the mid of the media section it belongs
+/// to if the body names one, along with whether it ends the candidates.
+///
+/// Lines other than media sections, mids, candidates and `a=end-of-candidates` are ignored.
+fn parse_sdpfrag(body: &str) -> RemoteCandidates {
+    let mut candidates = vec![];
+    let mut mid = None;
+    let mut end = false;
+    for line in body.lines().map(|line| line.trim_end()) {
+        if line.starts_with("m=") {
+            mid = None;
+        } else if let Some(attr) = line.strip_prefix("a=") {
+            if attr.starts_with("candidate:") {
+                candidates.push((attr.to_string(), mid.clone()));
+            } else if let Some(m) = attr.strip_prefix("mid:") {
+                mid = Some(m.to_string());
+            } else if attr == "end-of-candidates" {
+                end = true;
+            }
+        }
+    }
+    (candidates, end)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_sdpfrag_roundtrip() {
+        let candidates = vec![
+            "a=candidate:1 1 UDP 2122317823 192.168.1.2 50000 typ host".to_string(),
+            "candidate:2 1 UDP 1686052607 203.0.113.1 50000 typ srflx raddr 0.0.0.0 rport 0"
+                .to_string(),
+        ];
+        let (parsed, end) = parse_sdpfrag(&format_sdpfrag("", &candidates, true));
+        assert_eq!(
+            parsed,
+            vec![
+                (
+                    "candidate:1 1 UDP 2122317823 192.168.1.2 50000 typ host".to_string(),
+                    None
+                ),
+                (candidates[1].clone(), None),
+            ]
+        );
+        assert!(end);
+        assert_eq!(parse_sdpfrag(""), (vec![], false));
+    }
+
+    #[test]
+    pub fn test_sdpfrag_header() {
+        let sdp = "v=0\r\n\
+                   o=- 1 0 IN IP4 127.0.0.1\r\n\
+                   s=-\r\n\
+                   t=0 0\r\n\
+                   a=group:BUNDLE 0\r\n\
+                   m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+                   c=IN IP4 0.0.0.0\r\n\
+                   a=mid:0\r\n\
+                   a=ice-ufrag:abcd\r\n\
+                   a=ice-pwd:efghijklmnopqrstuvwxyz\r\n\
+                   a=candidate:1 1 UDP 2122317823 192.168.1.2 50000 typ host\r\n";
+        let header = sdpfrag_header(sdp);
+        assert_eq!(
+            header,
+            "a=ice-ufrag:abcd\r\n\
+             a=ice-pwd:efghijklmnopqrstuvwxyz\r\n\
+             m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+             a=mid:0\r\n"
+        );
+
+        let candidates =
+            vec!["candidate:1 1 UDP 2122317823 192.168.1.2 50000 typ host".to_string()];
+        assert_eq!(
+            parse_sdpfrag(&format_sdpfrag(&header, &candidates, true)),
+            (vec![(candidates[0].clone(), Some("0".to_string()))], true)
+        );
+    }
+
+    #[test]
+    pub fn test_parse_sdpfrag_mid() {
+        let body = "a=ice-ufrag:abcd\r\n\
+                    a=ice-pwd:efgh\r\n\
+                    m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+                    a=mid:data\r\n\
+                    a=candidate:1 1 UDP 2122317823 192.168.1.2 50000 typ host\r\n\
+                    m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+                    a=candidate:2 1 UDP 2122317823 192.168.1.2 50001 typ host\r\n";
+        assert_eq!(
+            parse_sdpfrag(body),
+            (
+                vec![
+                    (
+                        "candidate:1 1 UDP 2122317823 192.168.1.2 50000 typ host".to_string(),
+                        Some("data".to_string())
+                    ),
+                    (
+                        "candidate:2 1 UDP 2122317823 192.168.1.2 50001 typ host".to_string(),
+                        None
+                    ),
+                ],
+                false
+            )
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use base64::Engine as _;
+use hmac::Mac as _;
+
+type HmacSha256 = hmac::Hmac<sha2::Sha256>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AuthError {
+    #[error("missing credentials")]
+    MissingCredentials,
+
+    #[error("invalid credentials")]
+    InvalidCredentials,
+
+    #[error("expired credentials")]
+    ExpiredCredentials,
+}
+
+/// Authenticates offers before any connection is created for them.
+pub trait Authenticator: Send + Sync + 'static {
+    /// What authentication establishes about the client, e.g. a user ID.
+    type Identity: Send + Sync + 'static;
+
+    /// Authenticate the offer's request, returning the client's identity.
+    fn authenticate<'a>(
+        &'a self,
+        parts: &'a axum::http::request::Parts,
+    ) -> futures::future::BoxFuture<'a, Result<Self::Identity, AuthError>>;
+}
+
+/// The identity of a client, as established by the [`crate::ServeOptions::authenticator`].
+///
+/// It is available from [`crate::Connecting::identity`] and, once connected, from [`crate::Peer::identity`] if the
+/// server has a registry.
+#[derive(Clone)]
+pub struct Identity(std::sync::Arc<dyn std::any::Any + Send + Sync>);
+
+impl Identity {
+    /// The identity, if it is of the authenticator's identity type.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+}
+
+/// An [`Authenticator`] with its identity type erased, so it can be stored in the server.
+pub(crate) trait DynAuthenticator: Send + Sync {
+    fn authenticate<'a>(
+        &'a self,
+        parts: &'a axum::http::request::Parts,
+    ) -> futures::future::BoxFuture<'a, Result<Identity, AuthError>>;
+}
+
+impl<A: Authenticator> DynAuthenticator for A {
+    fn authenticate<'a>(
+        &'a self,
+        parts: &'a axum::http::request::Parts,
+    ) -> futures::future::BoxFuture<'a, Result<Identity, AuthError>> {
+        Box::pin(async move {
+            let identity = Authenticator::authenticate(self, parts).await?;
+            Ok(Identity(std::sync::Arc::new(identity)))
+        })
+    }
+}
+
+/// The token of an `Authorization: Bearer` header.
+fn bearer_token(parts: &axum::http::request::Parts) -> Result<&str, AuthError> {
+    let (scheme, token) = parts
+        .headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split_once(' '))
+        .ok_or(AuthError::MissingCredentials)?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return Err(AuthError::MissingCredentials);
+    }
+    Ok(token.trim())
+}
+
+/// Compare secrets without leaking where they differ through timing.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Authenticates clients by a fixed set of tokens in the `Authorization: Bearer` header.
+pub struct BearerAuthenticator<I> {
+    tokens: Vec<(String, I)>,
+}
+
+impl<I> BearerAuthenticator<I> {
+    pub fn new() -> Self {
+        Self { tokens: vec![] }
+    }
+
+    /// Accept the token, identifying whoever presents it as the identity.
+    pub fn token(mut self, token: impl Into<String>, identity: I) -> Self {
+        self.tokens.push((token.into(), identity));
+        self
+    }
+}
+
+impl<I> Default for BearerAuthenticator<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: Clone + Send + Sync + 'static> Authenticator for BearerAuthenticator<I> {
+    type Identity = I;
+
+    fn authenticate<'a>(
+        &'a self,
+        parts: &'a axum::http::request::Parts,
+    ) -> futures::future::BoxFuture<'a, Result<Self::Identity, AuthError>> {
+        Box::pin(async move {
+            let token = bearer_token(parts)?;
+            // Check every token, so the time taken does not reveal which one matched.
+            let mut identity = None;
+            for (t, i) in self.tokens.iter() {
+                if constant_time_eq(t.as_bytes(), token.as_bytes()) && identity.is_none() {
+                    identity = Some(i);
+                }
+            }
+            identity.cloned().ok_or(AuthError::InvalidCredentials)
+        })
+    }
+}
+
+/// Authenticates clients by tokens in the `Authorization: Bearer` header that are signed with a shared key, so they can
+/// be issued elsewhere without the server having to know them.
+///
+/// Tokens have the form `{subject}.{expires}.{signature}`, where `expires` is a Unix timestamp in seconds and
+/// `signature` is the unpadded base64url HMAC-SHA256 of `{subject}.{expires}`. The identity is the subject. Tokens
+/// whose expiry is too far in the future to represent never expire.
+pub struct HmacAuthenticator {
+    key: Vec<u8>,
+}
+
+impl HmacAuthenticator {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self { key: key.into() }
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+
+    /// Issue a token for the subject that is valid until the expiry time.
+    pub fn sign(&self, subject: &str, expires: std::time::SystemTime) -> String {
+        let expires = expires
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let payload = format!("{subject}.{expires}");
+        let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(self.mac(&payload).finalize().into_bytes());
+        format!("{payload}.{signature}")
+    }
+
+    /// Check the token's signature and expiry, returning its subject.
+    fn verify(&self, token: &str, now: std::time::SystemTime) -> Result<String, AuthError> {
+        let (payload, signature) = token
+            .rsplit_once('.')
+            .ok_or(AuthError::InvalidCredentials)?;
+        let (subject, expires) = payload
+            .rsplit_once('.')
+            .ok_or(AuthError::InvalidCredentials)?;
+        let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| AuthError::InvalidCredentials)?;
+        self.mac(payload)
+            .verify_slice(&signature)
+            .map_err(|_| AuthError::InvalidCredentials)?;
+
+        let expires: u64 = expires.parse().map_err(|_| AuthError::InvalidCredentials)?;
+        // An expiry too far in the future to represent never passes.
+        if matches!(
+            std::time::UNIX_EPOCH.checked_add(std::time::Duration::from_secs(expires)),
+            Some(expires) if now >= expires
+        ) {
+            return Err(AuthError::ExpiredCredentials);
+        }
+        Ok(subject.to_string())
+    }
+}
+
+impl Authenticator for HmacAuthenticator {
+    type Identity = String;
+
+    fn authenticate<'a>(
+        &'a self,
+        parts: &'a axum::http::request::Parts,
+    ) -> futures::future::BoxFuture<'a, Result<Self::Identity, AuthError>> {
+        Box::pin(async move { self.verify(bearer_token(parts)?, std::time::SystemTime::now()) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parts(authorization: Option<&str>) -> axum::http::request::Parts {
+        let mut req = axum::http::Request::builder();
+        if let Some(authorization) = authorization {
+            req = req.header(axum::http::header::AUTHORIZATION, authorization);
+        }
+        req.body(()).unwrap().into_parts().0
+    }
+
+    #[tokio::test]
+    pub async fn test_bearer_authenticator() {
+        let auth = BearerAuthenticator::new()
+            .token("alice-token", "alice")
+            .token("bob-token", "bob");
+
+        assert_eq!(
+            Authenticator::authenticate(&auth, &parts(Some("Bearer bob-token")))
+                .await
+                .unwrap(),
+            "bob"
+        );
+        assert!(matches!(
+            Authenticator::authenticate(&auth, &parts(Some("Bearer eve-token"))).await,
+            Err(AuthError::InvalidCredentials)
+        ));
+        assert!(matches!(
+            Authenticator::authenticate(&auth, &parts(Some("Basic YWxpY2U6"))).await,
+            Err(AuthError::MissingCredentials)
+        ));
+        assert!(matches!(
+            Authenticator::authenticate(&auth, &parts(None)).await,
+            Err(AuthError::MissingCredentials)
+        ));
+    }
+
+    #[tokio::test]
+    pub async fn test_hmac_authenticator() {
+        let auth = HmacAuthenticator::new("secret");
+        let now = std::time::SystemTime::now();
+        let token = auth.sign("alice.smith", now + std::time::Duration::from_secs(60));
+
+        assert_eq!(
+            Authenticator::authenticate(&auth, &parts(Some(&format!("Bearer {token}"))))
+                .await
+                .unwrap(),
+            "alice.smith"
+        );
+        assert!(matches!(
+            auth.verify(&token, now + std::time::Duration::from_secs(120)),
+            Err(AuthError::ExpiredCredentials)
+        ));
+        assert!(matches!(
+            auth.verify(&token.replacen("alice", "mallory", 1), now),
+            Err(AuthError::InvalidCredentials)
+        ));
+        assert!(matches!(
+            HmacAuthenticator::new("other secret").verify(&token, now),
+            Err(AuthError::InvalidCredentials)
+        ));
+
+        let payload = format!("bob.{}", u64::MAX);
+        let signature = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .encode(auth.mac(&payload).finalize().into_bytes());
+        assert_eq!(
+            auth.verify(&format!("{payload}.{signature}"), now).unwrap(),
+            "bob"
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_serve_authenticated() {
+        use futures::StreamExt as _;
+
+        let mut server = crate::test::serve(
+            crate::ServeOptions::new()
+                .authenticator(BearerAuthenticator::new().token("alice-token", "alice")),
+        )
+        .await;
+
+        let connect = |token| {
+            crate::test::connect(
+                dachannel_client::ConnectOptions::new().header(
+                    reqwest::header::AUTHORIZATION,
+                    reqwest::header::HeaderValue::from_static(token),
+                ),
+                dachannel::Connection::builder(Default::default()).unwrap(),
+                &server.url,
+            )
+        };
+
+        match connect("Bearer eve-token").await.unwrap() {
+            Err(dachannel_client::Error::Rejected { status, .. }) => {
+                assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+            }
+            r => panic!("expected rejection, got {:?}", r.map(|_| ())),
+        }
+
+        let client_jh = connect("Bearer alice-token");
+
+        let connecting = server.connecting_rx.next().await.unwrap();
+        assert_eq!(connecting.identity().unwrap().get::<&str>(), Some(&"alice"));
+        let _conn = connecting.await.unwrap();
+
+        client_jh.await.unwrap().unwrap();
+    }
+}
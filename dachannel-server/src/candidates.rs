@@ -0,0 +1,184 @@
+/// How the server's host candidates are advertised to clients.
+pub(crate) struct HostCandidates {
+    /// Only host candidates on these addresses are advertised. `0.0.0.0` allows every IPv4 address, and `::` allows
+    /// every address, as binding it is dual-stack.
+    bind_ips: Vec<std::net::IpAddr>,
+    /// If any, host candidates carry these addresses of the same family instead of their own, as for a 1:1 NAT.
+    public_ips: Vec<std::net::IpAddr>,
+}
+
+impl HostCandidates {
+    pub(crate) fn new(bind_ips: Vec<std::net::IpAddr>, public_ips: Vec<std::net::IpAddr>) -> Self {
+        Self {
+            bind_ips,
+            public_ips,
+        }
+    }
+
+    fn is_bound(&self, addr: std::net::IpAddr) -> bool {
+        self.bind_ips
+            .iter()
+            .any(|ip| *ip == addr || (ip.is_unspecified() && (ip.is_ipv6() || addr.is_ipv4())))
+    }
+
+    /// Rewrite a candidate for advertising.
+    ///
+    /// Host candidates on addresses that are not bound are dropped, and the rest are rewritten to carry the public IPs
+    /// of their family, if any. Rewritten candidates after the first get distinct foundations. Other candidates are left
+    /// as they are.
+    pub(crate) fn rewrite_candidate(&self, cand: &str) -> Vec<String> {
+        let (prefix, attr) = match cand.strip_prefix("a=") {
+            Some(attr) => ("a=", attr),
+            None => ("", cand),
+        };
+        let Some(fields) = attr.strip_prefix("candidate:") else {
+            return vec![cand.to_string()];
+        };
+        let fields = fields.split_ascii_whitespace().collect::<Vec<_>>();
+        let Some(addr) = fields
+            .get(4)
+            .and_then(|addr| addr.parse::<std::net::IpAddr>().ok())
+        else {
+            return vec![cand.to_string()];
+        };
+        if fields.get(6) != Some(&"typ") || fields.get(7) != Some(&"host") {
+            return vec![cand.to_string()];
+        }
+
+        if !self.is_bound(addr) {
+            return vec![];
+        }
+
+        let rewritten = self
+            .public_ips
+            .iter()
+            .filter(|ip| ip.is_ipv4() == addr.is_ipv4())
+            .enumerate()
+            .map(|(i, ip)| {
+                let foundation = if i == 0 {
+                    fields[0].to_string()
+                } else {
+                    format!("{}{i}", fields[0])
+                };
+                let ip = ip.to_string();
+                let mut fields = fields.clone();
+                fields[0] = &foundation;
+                fields[4] = &ip;
+                format!("{prefix}candidate:{}", fields.join(" "))
+            })
+            .collect::<Vec<_>>();
+        if rewritten.is_empty() {
+            vec![cand.to_string()]
+        } else {
+            rewritten
+        }
+    }
+
+    /// Rewrite the candidates of an SDP with [`HostCandidates::rewrite_candidate`].
+    pub(crate) fn rewrite_sdp(&self, sdp: &str) -> String {
+        let mut rewritten = String::with_capacity(sdp.len());
+        for line in sdp.lines() {
+            for line in self.rewrite_candidate(line) {
+                rewritten.push_str(&line);
+                rewritten.push_str("\r\n");
+            }
+        }
+        rewritten
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_rewrite_candidate() {
+        let host_candidates = HostCandidates::new(
+            vec!["::".parse().unwrap()],
+            vec![
+                "203.0.113.1".parse().unwrap(),
+                "203.0.113.2".parse().unwrap(),
+                "2001:db8::1".parse().unwrap(),
+            ],
+        );
+
+        assert_eq!(
+            host_candidates
+                .rewrite_candidate("a=candidate:1 1 UDP 2122317823 10.0.0.2 50000 typ host"),
+            vec![
+                "a=candidate:1 1 UDP 2122317823 203.0.113.1 50000 typ host".to_string(),
+                "a=candidate:11 1 UDP 2122317823 203.0.113.2 50000 typ host".to_string(),
+            ]
+        );
+        assert_eq!(
+            host_candidates
+                .rewrite_candidate("candidate:2 1 UDP 2122317823 fd00::2 50000 typ host"),
+            vec!["candidate:2 1 UDP 2122317823 2001:db8::1 50000 typ host".to_string()]
+        );
+
+        let srflx =
+            "a=candidate:3 1 UDP 1686052607 198.51.100.1 50000 typ srflx raddr 0.0.0.0 rport 0";
+        assert_eq!(
+            host_candidates.rewrite_candidate(srflx),
+            vec![srflx.to_string()]
+        );
+        let mdns = "a=candidate:4 1 UDP 2122317823 host.local 50000 typ host";
+        assert_eq!(
+            host_candidates.rewrite_candidate(mdns),
+            vec![mdns.to_string()]
+        );
+        let v4 = "a=candidate:5 1 UDP 2122317823 10.0.0.2 50000 typ host";
+        assert_eq!(
+            HostCandidates::new(
+                vec!["0.0.0.0".parse().unwrap()],
+                vec!["2001:db8::1".parse().unwrap()]
+            )
+            .rewrite_candidate(v4),
+            vec![v4.to_string()]
+        );
+    }
+
+    #[test]
+    pub fn test_rewrite_candidate_bind_ips() {
+        let host_candidates = HostCandidates::new(
+            vec!["10.0.0.2".parse().unwrap(), "fd00::2".parse().unwrap()],
+            vec![],
+        );
+
+        let bound_v4 = "a=candidate:1 1 UDP 2122317823 10.0.0.2 50000 typ host";
+        assert_eq!(
+            host_candidates.rewrite_candidate(bound_v4),
+            vec![bound_v4.to_string()]
+        );
+        assert!(host_candidates
+            .rewrite_candidate("a=candidate:2 1 UDP 2122317823 192.168.1.2 50000 typ host")
+            .is_empty());
+        let bound_v6 = "a=candidate:3 1 UDP 2122317823 fd00::2 50000 typ host";
+        assert_eq!(
+            host_candidates.rewrite_candidate(bound_v6),
+            vec![bound_v6.to_string()]
+        );
+        assert!(host_candidates
+            .rewrite_candidate("a=candidate:4 1 UDP 2122317823 fd00::3 50000 typ host")
+            .is_empty());
+
+        let dual_stack = HostCandidates::new(vec!["::".parse().unwrap()], vec![]);
+        assert_eq!(
+            dual_stack.rewrite_candidate(bound_v4),
+            vec![bound_v4.to_string()]
+        );
+    }
+
+    #[test]
+    pub fn test_rewrite_sdp() {
+        let sdp = "v=0\r\na=candidate:1 1 UDP 2122317823 10.0.0.2 50000 typ host\r\na=candidate:2 1 UDP 2122317823 192.168.1.2 50000 typ host\r\na=end-of-candidates\r\n";
+        assert_eq!(
+            HostCandidates::new(
+                vec!["10.0.0.2".parse().unwrap()],
+                vec!["203.0.113.1".parse().unwrap()]
+            )
+            .rewrite_sdp(sdp),
+            "v=0\r\na=candidate:1 1 UDP 2122317823 203.0.113.1 50000 typ host\r\na=end-of-candidates\r\n"
+        );
+    }
+}
@@ -1,8 +1,20 @@
 use datachannel_facade::platform::native::ConfigurationExt as _;
+use futures::FutureExt as _;
 use futures::SinkExt as _;
 use http_body_util::BodyExt as _;
 use tokio::io::AsyncWriteExt;
 
+mod auth;
+mod candidates;
+mod registry;
+mod session;
+mod shutdown;
+mod trickle;
+
+pub use auth::*;
+pub use registry::*;
+pub use shutdown::*;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("dachannel: {0}")]
@@ -20,13 +32,23 @@ pub enum Error {
 
 /// A Future that is an in-progress connection attempt from a remote client.
 ///
-/// This Future may be awaited on to complete the connection, or dropped to abort it.
+/// This Future may be awaited on (or [`Connecting::accept`]ed) to complete the connection, or
+/// [`Connecting::reject`]ed to refuse it with an HTTP status. Dropping it refuses the connection with
+/// `503 Service Unavailable`. With trickle ICE, it completes once the connection is connected.
+///
+/// The connection is closed if the client deletes its session, which it does when it closes its side of the connection.
 pub struct Connecting {
     parts: axum::http::request::Parts,
     remote_addr: std::net::SocketAddr,
     connection_builder: dachannel::ConnectionBuilder,
     body: axum::body::Body,
     answer_sdp_tx: Option<tokio::io::DuplexStream>,
+    decision_tx: Option<tokio::sync::oneshot::Sender<Decision>>,
+    identity: Option<Identity>,
+    peer: Option<registry::PendingPeer>,
+    session: Option<session::SessionGuard>,
+    trickle: Option<trickle::Trickle>,
+    host_candidates: std::sync::Arc<candidates::HostCandidates>,
 }
 
 impl Connecting {
@@ -36,7 +58,13 @@ impl Connecting {
         label: &str,
         options: dachannel::DataChannelOptions,
     ) -> Result<dachannel::Channel, dachannel::Error> {
-        self.connection_builder.create_data_channel(label, options)
+        let channel = self
+            .connection_builder
+            .create_data_channel(label, options)?;
+        if let Some(peer) = self.peer.as_ref() {
+            peer.add_channel(label, &channel);
+        }
+        Ok(channel)
     }
 
     /// The HTTP Authorization header, if any.
@@ -52,6 +80,36 @@ impl Connecting {
     pub fn remote_addr(&self) -> &std::net::SocketAddr {
         &self.remote_addr
     }
+
+    /// The client's identity, if the server has an authenticator.
+    pub fn identity(&self) -> Option<&Identity> {
+        self.identity.as_ref()
+    }
+
+    /// The ID the connection has in the server's registry once it completes, if the server has a registry.
+    pub fn peer_id(&self) -> Option<PeerId> {
+        self.peer.as_ref().map(|peer| peer.id())
+    }
+
+    /// Accept the connection, completing it. This is the same as awaiting on the Connecting itself.
+    pub fn accept(self) -> <Self as std::future::IntoFuture>::IntoFuture {
+        std::future::IntoFuture::into_future(self)
+    }
+
+    /// Refuse the connection, responding to the offer with the given status and body instead of an answer.
+    ///
+    /// The status should be an error status, e.g. `401 Unauthorized` or `503 Service Unavailable`.
+    pub fn reject(mut self, status: axum::http::StatusCode, body: impl Into<String>) {
+        if let Some(decision_tx) = self.decision_tx.take() {
+            let _ = decision_tx.send(Decision::Reject(status, body.into()));
+        }
+    }
+}
+
+/// How the application decided to handle an offer.
+enum Decision {
+    Accept,
+    Reject(axum::http::StatusCode, String),
 }
 
 impl std::future::IntoFuture for Connecting {
@@ -60,11 +118,19 @@ impl std::future::IntoFuture for Connecting {
 
     fn into_future(mut self) -> Self::IntoFuture {
         Box::pin(async move {
+            if let Some(decision_tx) = self.decision_tx.take() {
+                let _ = decision_tx.send(Decision::Accept);
+            }
+
             let offer_sdp = String::from_utf8(self.body.collect().await?.to_bytes().to_vec())
                 .map_err(|_| Error::MalformedBody)?;
 
-            let conn = self.connection_builder.build();
+            let mut conn = self.connection_builder.build();
+            let session = self.session.take().unwrap().attach(&conn)?;
 
+            if let Some(trickle) = self.trickle.as_ref() {
+                trickle.set_offer(&offer_sdp);
+            }
             conn.set_remote_description(&dachannel::Description {
                 type_: dachannel::SdpType::Offer,
                 sdp: offer_sdp,
@@ -72,11 +138,25 @@ impl std::future::IntoFuture for Connecting {
             .await?;
             conn.set_local_description(dachannel::SdpType::Answer)
                 .await?;
-            conn.ice_candidates_gathered().await;
+            if self.trickle.is_none() {
+                let mut connection_state = conn.watch_state();
+                futures::select! {
+                    () = conn.ice_candidates_gathered().fuse() => {}
+                    _ = connection_state.wait_for(|state| {
+                        matches!(
+                            state,
+                            dachannel::PeerConnectionState::Failed
+                                | dachannel::PeerConnectionState::Closed
+                        )
+                    }).fuse() => {
+                        return Err(Error::Closed);
+                    }
+                }
+            }
 
             let answer_sdp = conn
                 .local_description()?
-                .map(|v| v.sdp)
+                .map(|v| self.host_candidates.rewrite_sdp(&v.sdp))
                 .unwrap_or_else(|| "".to_string());
 
             self.answer_sdp_tx
@@ -86,6 +166,14 @@ impl std::future::IntoFuture for Connecting {
                 .await
                 .map_err(|_| Error::Closed)?;
 
+            if let Some(trickle) = self.trickle.take() {
+                trickle.run(&mut conn, &self.host_candidates).await?;
+            }
+
+            session.set_connected();
+            if let Some(peer) = self.peer.take() {
+                peer.register(&conn, self.identity.take());
+            }
             Ok(conn)
         })
     }
@@ -94,53 +182,132 @@ impl std::future::IntoFuture for Connecting {
 async fn offer(
     axum::extract::State(state): axum::extract::State<std::sync::Arc<AppState>>,
     axum::extract::ConnectInfo(remote_addr): axum::extract::ConnectInfo<std::net::SocketAddr>,
+    axum::extract::OriginalUri(offer_uri): axum::extract::OriginalUri,
     req: axum::extract::Request,
 ) -> Result<impl axum::response::IntoResponse, axum::http::StatusCode> {
-    let (parts, body) = req.into_parts();
+    if state.shutdown.is_shutting_down() {
+        return Err(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    let (mut parts, body) = req.into_parts();
+
+    let identity = match state.authenticator.as_ref() {
+        Some(authenticator) => Some(authenticator.authenticate(&parts).await.map_err(|e| {
+            log::debug!("failed to authenticate offer from {remote_addr}: {e}");
+            axum::http::StatusCode::UNAUTHORIZED
+        })?),
+        None => None,
+    };
+    if let Some(identity) = identity.as_ref() {
+        parts.extensions.insert(identity.clone());
+    }
 
     let mut config: dachannel::Configuration = Default::default();
     config.ice_servers = state.ice_servers.clone();
-    config.set_bind(
-        state.bind_addr.ip(),
-        state.bind_addr.port(),
-        state.bind_addr.port(),
-    );
+    let (port_range_begin, port_range_end) = state.udp_port_range;
+    let bind_ip = match state.udp_bind_ips.as_slice() {
+        [bind_ip] => *bind_ip,
+        _ => std::net::Ipv6Addr::UNSPECIFIED.into(),
+    };
+    config.set_bind(bind_ip, port_range_begin, port_range_end);
     config.set_enable_ice_udp_mux(true);
+    if let Some(configure) = state.configure.as_ref() {
+        configure(&parts, &mut config);
+    }
 
     let connection_builder = dachannel::Connection::builder(config).map_err(|e| {
         log::error!("failed to create connection: {e}");
         axum::http::StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
+    let (candidates, trickle) = if state.trickle_ice {
+        let (candidates, trickle) = trickle::channel();
+        (Some(candidates), Some(trickle))
+    } else {
+        (None, None)
+    };
+    let session = session::SessionGuard::new(std::sync::Arc::clone(&state.sessions), candidates);
+    let location = session.location(&offer_uri);
+    let session_ref = std::sync::Arc::clone(session.session());
+
     let (answer_sdp_tx, answer_sdp_rx) = tokio::io::duplex(4096);
-    state
-        .connecting_tx
-        .lock()
-        .await
-        .send(Connecting {
-            parts,
-            remote_addr,
-            connection_builder,
-            body,
-            answer_sdp_tx: Some(answer_sdp_tx),
-        })
-        .await
-        .map_err(|_e| axum::http::StatusCode::SERVICE_UNAVAILABLE)?;
+    let (decision_tx, decision_rx) = tokio::sync::oneshot::channel();
+    let decision = async {
+        state
+            .connecting_tx
+            .lock()
+            .await
+            .send(Connecting {
+                parts,
+                remote_addr,
+                connection_builder,
+                body,
+                answer_sdp_tx: Some(answer_sdp_tx),
+                decision_tx: Some(decision_tx),
+                identity,
+                peer: state.registry.clone().map(registry::PendingPeer::new),
+                session: Some(session),
+                trickle,
+                host_candidates: std::sync::Arc::clone(&state.host_candidates),
+            })
+            .await
+            .map_err(|_e| axum::http::StatusCode::SERVICE_UNAVAILABLE)?;
+        decision_rx
+            .await
+            .map_err(|_e| axum::http::StatusCode::SERVICE_UNAVAILABLE)
+    };
+
+    // The session is deleted if the server shuts down before the offer is decided on.
+    let decision = tokio::select! {
+        decision = decision => decision?,
+        () = session_ref.deleted() => {
+            return Err(axum::http::StatusCode::SERVICE_UNAVAILABLE);
+        }
+    };
+    if let Decision::Reject(status, body) = decision {
+        return Ok(axum::response::IntoResponse::into_response((status, body)));
+    }
 
-    Ok(axum::body::Body::from_stream(
+    let mut res = axum::response::IntoResponse::into_response(axum::body::Body::from_stream(
         tokio_util::io::ReaderStream::new(answer_sdp_rx),
-    ))
+    ));
+    res.headers_mut().insert(
+        axum::http::header::LOCATION,
+        axum::http::HeaderValue::try_from(location).unwrap(),
+    );
+    Ok(res)
 }
 
+type Configure =
+    Box<dyn Fn(&axum::http::request::Parts, &mut dachannel::Configuration) + Send + Sync>;
+
 struct AppState {
-    bind_addr: std::net::SocketAddr,
+    udp_bind_ips: Vec<std::net::IpAddr>,
+    udp_port_range: (u16, u16),
+    host_candidates: std::sync::Arc<candidates::HostCandidates>,
     ice_servers: Vec<dachannel::IceServer>,
+    trickle_ice: bool,
+    authenticator: Option<Box<dyn auth::DynAuthenticator>>,
+    registry: Option<Registry>,
+    configure: Option<Configure>,
+    sessions: std::sync::Arc<session::Sessions>,
+    shutdown: ShutdownHandle,
     connecting_tx: tokio::sync::Mutex<futures::channel::mpsc::Sender<Connecting>>,
 }
 
 pub struct ServeOptions {
     ice_servers: Vec<dachannel::IceServer>,
     backlog: usize,
+    trickle_ice: bool,
+    udp_bind_ips: Option<Vec<std::net::IpAddr>>,
+    udp_port_range: Option<(u16, u16)>,
+    public_ips: Vec<std::net::IpAddr>,
+    authenticator: Option<Box<dyn auth::DynAuthenticator>>,
+    registry: Option<Registry>,
+    configure: Option<Configure>,
+    cors: Option<tower_http::cors::CorsLayer>,
+    body_limit: usize,
+    shutdown: ShutdownHandle,
 }
 
 impl ServeOptions {
@@ -148,6 +315,27 @@ impl ServeOptions {
         Self {
             ice_servers: vec![],
             backlog: 128,
+            trickle_ice: false,
+            udp_bind_ips: None,
+            udp_port_range: None,
+            public_ips: vec![],
+            authenticator: None,
+            registry: None,
+            configure: None,
+            cors: Some(
+                tower_http::cors::CorsLayer::new()
+                    .allow_headers([axum::http::header::AUTHORIZATION, "*".try_into().unwrap()])
+                    .allow_methods([
+                        axum::http::Method::POST,
+                        axum::http::Method::PATCH,
+                        axum::http::Method::GET,
+                        axum::http::Method::DELETE,
+                    ])
+                    .allow_origin(tower_http::cors::Any)
+                    .expose_headers([axum::http::header::LOCATION]),
+            ),
+            body_limit: 4096,
+            shutdown: ShutdownHandle::new(),
         }
     }
 
@@ -161,41 +349,290 @@ impl ServeOptions {
         self
     }
 
+    /// Answer offers immediately instead of waiting for ICE candidates to be gathered, and exchange candidates with the
+    /// client afterwards.
+    ///
+    /// The client sends its candidates to the session resource in the answer's `Location` header with `PATCH`
+    /// requests, and long-polls it with `GET` requests for the server's candidates, both as
+    /// `application/trickle-ice-sdpfrag` bodies.
+    pub fn trickle_ice(mut self, trickle_ice: bool) -> Self {
+        self.trickle_ice = trickle_ice;
+        self
+    }
+
+    /// The address to bind connections' UDP sockets to. All connections share the port through ICE UDP mux.
+    ///
+    /// By default, [`ServeOptions::serve`] uses the listener's address, and [`ServeOptions::into_router`] uses an
+    /// ephemeral port on all IPv4 interfaces.
+    pub fn udp_bind_addr(mut self, udp_bind_addr: std::net::SocketAddr) -> Self {
+        self.udp_bind_ips = Some(vec![udp_bind_addr.ip()]);
+        self.udp_port_range = Some((udp_bind_addr.port(), udp_bind_addr.port()));
+        self
+    }
+
+    /// The IPs to bind connections' UDP sockets to, replacing the IP of the UDP bind address, e.g. `0.0.0.0` and `::`
+    /// to serve both IPv4 and IPv6 clients, or the addresses of several interfaces.
+    ///
+    /// With more than one IP, connections bind to `::` in dual-stack mode, which requires IPv6 support, and only host
+    /// candidates on the given IPs are advertised. `::` on its own also serves both IPv4 and IPv6 clients.
+    pub fn udp_bind_ips(mut self, udp_bind_ips: Vec<std::net::IpAddr>) -> Self {
+        self.udp_bind_ips = Some(udp_bind_ips);
+        self
+    }
+
+    /// The range of ports, inclusive, to bind connections' UDP sockets to, replacing the port of the UDP bind address.
+    ///
+    /// All connections share a single port from the range through ICE UDP mux. To have each connection bind its own
+    /// ports from the range instead, disable ICE UDP mux in [`ServeOptions::configure`].
+    pub fn udp_port_range(mut self, begin: u16, end: u16) -> Self {
+        self.udp_port_range = Some((begin, end));
+        self
+    }
+
+    /// Public IPs to advertise instead of the server's own addresses, for servers behind a 1:1 NAT.
+    ///
+    /// Host candidates in answers, and in candidates sent with trickle ICE, are rewritten to carry the public IPs of the
+    /// same address family, keeping their ports.
+    pub fn public_ips(mut self, public_ips: Vec<std::net::IpAddr>) -> Self {
+        self.public_ips = public_ips;
+        self
+    }
+
+    /// Authenticate offers before creating connections for them. Offers that fail authentication are refused with
+    /// `401 Unauthorized`.
+    ///
+    /// The client's identity is available from [`Connecting::identity`] and, once connected, from the connection's
+    /// [`Peer::identity`] if the server has a registry.
+    pub fn authenticator(mut self, authenticator: impl Authenticator) -> Self {
+        self.authenticator = Some(Box::new(authenticator));
+        self
+    }
+
+    /// Add connections to the registry once they are set up.
+    pub fn registry(mut self, registry: Registry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Adjust the configuration of each connection, based on the offer's request.
+    ///
+    /// The callback receives the configuration the server would otherwise use, i.e. with the server's ICE servers, bound
+    /// to the UDP bind IPs and port range, and with ICE UDP mux enabled. If the server has an authenticator, the
+    /// client's [`Identity`] is available from the request's extensions.
+    pub fn configure(
+        mut self,
+        configure: impl Fn(&axum::http::request::Parts, &mut dachannel::Configuration)
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.configure = Some(Box::new(configure));
+        self
+    }
+
+    /// The CORS layer applied to the routes, or `None` to leave CORS to the application.
+    ///
+    /// By default, requests from any origin are allowed, and the `Location` header is exposed to them.
+    pub fn cors(mut self, cors: Option<tower_http::cors::CorsLayer>) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    /// The maximum size of request bodies, in bytes. Defaults to 4096.
+    pub fn body_limit(mut self, body_limit: usize) -> Self {
+        self.body_limit = body_limit;
+        self
+    }
+
+    /// A handle for shutting down the server gracefully.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    /// Build the routes for serving offers, yielding a [`Connecting`] for each.
+    ///
+    /// Offers are posted to `/`, and every answer's response carries a `Location` header with the session resource,
+    /// relative to the offer endpoint. A `DELETE` request to it closes the connection. The router may be nested into an
+    /// application's own router at any path, and must be served with
+    /// [`axum::Router::into_make_service_with_connect_info`] for [`std::net::SocketAddr`].
+    pub fn into_router(self) -> (axum::Router, futures::channel::mpsc::Receiver<Connecting>) {
+        let (connecting_tx, connecting_rx) = futures::channel::mpsc::channel(self.backlog);
+        let udp_bind_ips = match self.udp_bind_ips {
+            Some(udp_bind_ips) if !udp_bind_ips.is_empty() => udp_bind_ips,
+            _ => vec![std::net::Ipv4Addr::UNSPECIFIED.into()],
+        };
+        let router = axum::Router::new()
+            .route("/", axum::routing::post(offer))
+            .route(
+                "/sessions/:id",
+                axum::routing::patch(trickle::patch_candidates)
+                    .get(trickle::get_candidates)
+                    .delete(session::delete_session),
+            )
+            .with_state(std::sync::Arc::new(AppState {
+                host_candidates: std::sync::Arc::new(candidates::HostCandidates::new(
+                    udp_bind_ips.clone(),
+                    self.public_ips,
+                )),
+                udp_bind_ips,
+                udp_port_range: self.udp_port_range.unwrap_or((0, 0)),
+                ice_servers: self.ice_servers,
+                trickle_ice: self.trickle_ice,
+                authenticator: self.authenticator,
+                registry: self.registry,
+                configure: self.configure,
+                sessions: std::sync::Arc::clone(&self.shutdown.sessions),
+                shutdown: self.shutdown,
+                connecting_tx: tokio::sync::Mutex::new(connecting_tx),
+            }))
+            .layer(tower_http::limit::RequestBodyLimitLayer::new(
+                self.body_limit,
+            ));
+        let router = match self.cors {
+            Some(cors) => router.layer(cors),
+            None => router,
+        };
+        (router, connecting_rx)
+    }
+
+    /// Serve offers from clients on the listener, yielding a [`Connecting`] for each.
+    ///
+    /// See [`ServeOptions::into_router`] for the routes served. The returned future completes once the server has been
+    /// shut down through its [`ServeOptions::shutdown_handle`].
     pub fn serve(
-        self,
+        mut self,
         listener: tokio::net::TcpListener,
     ) -> (
         impl std::future::Future<Output = Result<(), std::io::Error>>,
         futures::channel::mpsc::Receiver<Connecting>,
     ) {
-        let (connecting_tx, connecting_rx) = futures::channel::mpsc::channel(self.backlog);
+        if let Ok(local_addr) = listener.local_addr() {
+            self.udp_bind_ips.get_or_insert(vec![local_addr.ip()]);
+            self.udp_port_range
+                .get_or_insert((local_addr.port(), local_addr.port()));
+        }
+        let shutdown = self.shutdown_handle();
+        let (router, connecting_rx) = self.into_router();
         (
-            (move || async move {
-                let bind_addr = listener.local_addr()?;
+            async move {
                 axum::serve(
                     listener,
-                    axum::Router::new()
-                        .route("/", axum::routing::post(offer))
-                        .with_state(std::sync::Arc::new(AppState {
-                            bind_addr,
-                            ice_servers: self.ice_servers,
-                            connecting_tx: tokio::sync::Mutex::new(connecting_tx),
-                        }))
-                        .layer(
-                            tower_http::cors::CorsLayer::new()
-                                .allow_headers([
-                                    axum::http::header::AUTHORIZATION,
-                                    "*".try_into().unwrap(),
-                                ])
-                                .allow_methods([axum::http::Method::POST])
-                                .allow_origin(tower_http::cors::Any),
-                        )
-                        .layer(tower_http::limit::RequestBodyLimitLayer::new(4096))
-                        .into_make_service_with_connect_info::<std::net::SocketAddr>(),
+                    router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
                 )
+                .with_graceful_shutdown(async move { shutdown.shutting_down().await })
                 .await
-            })(),
+            },
             connecting_rx,
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A server started on a local port for a test.
+    pub(crate) struct TestServer {
+        pub(crate) url: String,
+        pub(crate) connecting_rx: futures::channel::mpsc::Receiver<Connecting>,
+        pub(crate) shutdown_handle: ShutdownHandle,
+        pub(crate) serve_jh: tokio::task::JoinHandle<()>,
+    }
+
+    /// Start a server on a local port with the options.
+    pub(crate) async fn serve(options: ServeOptions) -> TestServer {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let shutdown_handle = options.shutdown_handle();
+        let (serve_fut, connecting_rx) = options.serve(listener);
+        let serve_jh = tokio::spawn(async move {
+            serve_fut.await.unwrap();
+        });
+
+        TestServer {
+            url: format!("http://127.0.0.1:{}", local_addr.port()),
+            connecting_rx,
+            shutdown_handle,
+            serve_jh,
+        }
+    }
+
+    /// Connect the connection to the server at the URL in the background.
+    pub(crate) fn connect(
+        options: dachannel_client::ConnectOptions,
+        cb: dachannel::ConnectionBuilder,
+        url: &str,
+    ) -> tokio::task::JoinHandle<Result<dachannel::Connection, dachannel_client::Error>> {
+        let url = url.to_string();
+        tokio::spawn(async move { options.connect(cb, &url).await })
+    }
+
+    #[tokio::test]
+    pub async fn test_nested_router() {
+        use futures::StreamExt as _;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let local_addr = listener.local_addr().unwrap();
+
+        let (router, mut connecting_rx) = ServeOptions::new()
+            .trickle_ice(true)
+            .udp_bind_addr(local_addr)
+            .into_router();
+
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                axum::Router::new()
+                    .nest("/rtc", router)
+                    .into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        let client_jh = connect(
+            dachannel_client::ConnectOptions::new().trickle_ice(true),
+            dachannel::Connection::builder(Default::default()).unwrap(),
+            &format!("http://127.0.0.1:{}/rtc", local_addr.port()),
+        );
+
+        let connecting = connecting_rx.next().await.unwrap();
+        let conn = connecting.await.unwrap();
+        assert_eq!(conn.state(), dachannel::PeerConnectionState::Connected);
+
+        client_jh.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn test_configure() {
+        use futures::StreamExt as _;
+
+        let (configured_tx, mut configured_rx) = futures::channel::mpsc::unbounded();
+        let mut server = serve(ServeOptions::new().configure(move |parts, config| {
+            config.ice_servers.clear();
+            configured_tx
+                .unbounded_send(parts.headers.get("x-tenant").cloned())
+                .unwrap();
+        }))
+        .await;
+
+        let client_jh = connect(
+            dachannel_client::ConnectOptions::new().header(
+                "x-tenant",
+                reqwest::header::HeaderValue::from_static("acme"),
+            ),
+            dachannel::Connection::builder(Default::default()).unwrap(),
+            &server.url,
+        );
+
+        let connecting = server.connecting_rx.next().await.unwrap();
+        assert_eq!(
+            configured_rx.next().await.unwrap(),
+            Some(axum::http::HeaderValue::from_static("acme"))
+        );
+        let _conn = connecting.await.unwrap();
+
+        client_jh.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+/// The ID of a connection in a [`Registry`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PeerId(u64);
+
+impl std::fmt::Display for PeerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+type Channels =
+    std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, dachannel::Sender>>>;
+
+/// A connection in a [`Registry`].
+#[derive(Clone)]
+pub struct Peer {
+    id: PeerId,
+    handle: dachannel::ConnectionHandle,
+    identity: Option<crate::Identity>,
+    channels: Channels,
+}
+
+impl Peer {
+    pub fn id(&self) -> PeerId {
+        self.id
+    }
+
+    /// The current state of the connection.
+    pub fn state(&self) -> dachannel::PeerConnectionState {
+        self.handle.state()
+    }
+
+    /// The client's identity, if the server has an authenticator.
+    pub fn identity(&self) -> Option<&crate::Identity> {
+        self.identity.as_ref()
+    }
+
+    /// A sender for the connection's registered channel with the label, if any.
+    pub fn channel(&self, label: &str) -> Option<dachannel::Sender> {
+        self.channels.lock().unwrap().get(label).cloned()
+    }
+}
+
+/// Tracks a server's connections by ID, so they can be looked up, messaged and disconnected.
+///
+/// Connections are added once their [`crate::Connecting`] completes, with the channels created on it, and removed once
+/// they fail or are closed. A connection's ID is available from [`crate::Connecting::peer_id`].
+///
+/// The registry keeps a [`dachannel::Sender`] for each registered channel, so channels stay open until their connection
+/// is removed or they are closed explicitly.
+#[derive(Clone, Default)]
+pub struct Registry {
+    inner: std::sync::Arc<RegistryInner>,
+}
+
+#[derive(Default)]
+struct RegistryInner {
+    next_id: std::sync::atomic::AtomicU64,
+    peers: std::sync::Mutex<std::collections::HashMap<PeerId, Peer>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The connection with the ID, if it is still registered.
+    pub fn get(&self, id: PeerId) -> Option<Peer> {
+        self.inner.peers.lock().unwrap().get(&id).cloned()
+    }
+
+    /// All registered connections.
+    pub fn peers(&self) -> Vec<Peer> {
+        self.inner.peers.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Register a channel of the connection with the ID, e.g. one accepted from the client, replacing any registered
+    /// channel with the same label.
+    ///
+    /// Returns whether the connection is still registered.
+    pub fn add_channel(
+        &self,
+        id: PeerId,
+        channel: &dachannel::Channel,
+    ) -> Result<bool, dachannel::Error> {
+        let Some(peer) = self.get(id) else {
+            return Ok(false);
+        };
+        peer.channels
+            .lock()
+            .unwrap()
+            .insert(channel.label()?, channel.sender());
+        Ok(true)
+    }
+
+    /// Send a binary datagram on the channel with the label of every registered connection that has one.
+    ///
+    /// Sends are made concurrently, and each waits for its channel's send buffer to drain if it is full. Returns the
+    /// number of connections the datagram was sent to.
+    pub async fn broadcast(&self, label: &str, buf: &[u8]) -> usize {
+        let senders = self
+            .peers()
+            .into_iter()
+            .filter_map(|peer| peer.channel(label).map(|sender| (peer.id, sender)))
+            .collect::<Vec<_>>();
+        futures::future::join_all(senders.iter().map(|(id, sender)| async move {
+            match sender.send_binary(buf).await {
+                Ok(()) => true,
+                Err(e) => {
+                    log::debug!("failed to broadcast to peer {id} on {label:?}: {e}");
+                    false
+                }
+            }
+        }))
+        .await
+        .into_iter()
+        .filter(|sent| *sent)
+        .count()
+    }
+
+    /// Close the connection with the ID and remove it.
+    ///
+    /// Returns whether the connection was registered.
+    pub fn disconnect(&self, id: PeerId) -> Result<bool, dachannel::Error> {
+        let Some(peer) = self.inner.peers.lock().unwrap().remove(&id) else {
+            return Ok(false);
+        };
+        peer.handle.close()?;
+        Ok(true)
+    }
+}
+
+/// A connection that will be added to a [`Registry`] once it is set up.
+///
+/// Its ID is allocated up front, so it is known before the connection completes.
+pub(crate) struct PendingPeer {
+    id: PeerId,
+    registry: Registry,
+    channels: Channels,
+}
+
+impl PendingPeer {
+    pub(crate) fn new(registry: Registry) -> Self {
+        let id = PeerId(
+            registry
+                .inner
+                .next_id
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        );
+        Self {
+            id,
+            registry,
+            channels: Default::default(),
+        }
+    }
+
+    pub(crate) fn id(&self) -> PeerId {
+        self.id
+    }
+
+    pub(crate) fn add_channel(&self, label: &str, channel: &dachannel::Channel) {
+        self.channels
+            .lock()
+            .unwrap()
+            .insert(label.to_string(), channel.sender());
+    }
+
+    /// Add the connection to the registry, removing it again once it fails or is closed.
+    pub(crate) fn register(self, conn: &dachannel::Connection, identity: Option<crate::Identity>) {
+        let id = self.id;
+        let handle = conn.handle();
+        let mut connection_state = handle.watch_state();
+        self.registry.inner.peers.lock().unwrap().insert(
+            id,
+            Peer {
+                id,
+                handle,
+                identity,
+                channels: self.channels,
+            },
+        );
+
+        let registry = std::sync::Arc::downgrade(&self.registry.inner);
+        tokio::spawn(async move {
+            connection_state
+                .wait_for(|state| {
+                    matches!(
+                        state,
+                        dachannel::PeerConnectionState::Failed
+                            | dachannel::PeerConnectionState::Closed
+                    )
+                })
+                .await;
+            if let Some(registry) = registry.upgrade() {
+                registry.peers.lock().unwrap().remove(&id);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    pub async fn test_registry() {
+        use futures::StreamExt as _;
+
+        let options = || dachannel::DataChannelOptions {
+            negotiated: true,
+            id: Some(1),
+            ..Default::default()
+        };
+
+        let registry = Registry::new();
+        let mut server =
+            crate::test::serve(crate::ServeOptions::new().registry(registry.clone())).await;
+
+        let cb = dachannel::Connection::builder(Default::default()).unwrap();
+        let mut client_dc = cb.create_data_channel("chat", options()).unwrap();
+        let client_jh =
+            crate::test::connect(dachannel_client::ConnectOptions::new(), cb, &server.url);
+
+        let connecting = server.connecting_rx.next().await.unwrap();
+        let _dc = connecting.create_data_channel("chat", options()).unwrap();
+
+        let id = connecting.peer_id().unwrap();
+        let conn = connecting.await.unwrap();
+        conn.wait_connected(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        let _client_conn = client_jh.await.unwrap().unwrap();
+        assert_eq!(registry.peers().len(), 1);
+        assert_eq!(
+            registry.get(id).unwrap().state(),
+            dachannel::PeerConnectionState::Connected
+        );
+
+        assert_eq!(registry.broadcast("chat", b"hello everyone").await, 1);
+        assert_eq!(registry.broadcast("other", b"hello nobody").await, 0);
+        assert_eq!(
+            client_dc.recv().await.unwrap(),
+            dachannel::Message::Binary(b"hello everyone".to_vec())
+        );
+
+        assert!(registry.disconnect(id).unwrap());
+        assert!(registry.get(id).is_none());
+        assert_eq!(conn.state(), dachannel::PeerConnectionState::Closed);
+    }
+}
@@ -0,0 +1,213 @@
+/// The HTTP side of a connection, addressed by the session resource returned in the offer's `Location` header.
+pub(crate) struct Session {
+    /// The candidate exchange, if the session uses trickle ICE.
+    pub(crate) candidates: Option<std::sync::Arc<crate::trickle::Candidates>>,
+    connection: std::sync::Mutex<SessionConnection>,
+    deleted: tokio_util::sync::CancellationToken,
+}
+
+enum SessionConnection {
+    /// The offer has not been accepted yet.
+    Pending,
+    /// The connection is being set up.
+    Connecting(dachannel::ConnectionHandle),
+    /// The connection has been handed to the application.
+    Connected(dachannel::ConnectionHandle),
+    Deleted,
+}
+
+impl Session {
+    /// Whether the connection is still being set up.
+    pub(crate) fn is_connecting(&self) -> bool {
+        matches!(
+            *self.connection.lock().unwrap(),
+            SessionConnection::Pending | SessionConnection::Connecting(_)
+        )
+    }
+
+    /// Mark the connection as set up.
+    pub(crate) fn set_connected(&self) {
+        let mut connection = self.connection.lock().unwrap();
+        if let SessionConnection::Connecting(handle) = &*connection {
+            *connection = SessionConnection::Connected(handle.clone());
+        }
+    }
+
+    /// Wait for the session to be deleted.
+    pub(crate) async fn deleted(&self) {
+        self.deleted.cancelled().await
+    }
+
+    /// Close the session's connection, or make sure it is closed as soon as it is established.
+    fn delete(&self) {
+        let connection = std::mem::replace(
+            &mut *self.connection.lock().unwrap(),
+            SessionConnection::Deleted,
+        );
+        self.deleted.cancel();
+        if let SessionConnection::Connecting(handle) | SessionConnection::Connected(handle) =
+            connection
+        {
+            if let Err(e) = handle.close() {
+                log::warn!("failed to close deleted session's connection: {e}");
+            }
+        }
+    }
+}
+
+/// The sessions of a server, by ID.
+#[derive(Default)]
+pub(crate) struct Sessions {
+    sessions: std::sync::Mutex<std::collections::HashMap<String, std::sync::Arc<Session>>>,
+    removed: tokio::sync::Notify,
+}
+
+impl Sessions {
+    fn insert(&self, id: String, session: std::sync::Arc<Session>) {
+        self.sessions.lock().unwrap().insert(id, session);
+    }
+
+    fn remove(&self, id: &str) -> Option<std::sync::Arc<Session>> {
+        let session = self.sessions.lock().unwrap().remove(id);
+        self.removed.notify_waiters();
+        session
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Result<std::sync::Arc<Session>, axum::http::StatusCode> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or(axum::http::StatusCode::NOT_FOUND)
+    }
+
+    /// Wait until no session matches the predicate.
+    pub(crate) async fn wait_until_none(&self, f: impl Fn(&Session) -> bool) {
+        loop {
+            let removed = self.removed.notified();
+            tokio::pin!(removed);
+            removed.as_mut().enable();
+            if !self.sessions.lock().unwrap().values().any(|s| f(s)) {
+                return;
+            }
+            removed.await;
+        }
+    }
+
+    /// Delete every session that matches the predicate.
+    pub(crate) fn delete_all(&self, f: impl Fn(&Session) -> bool) {
+        let deleted = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let ids = sessions
+                .iter()
+                .filter(|(_, s)| f(s))
+                .map(|(id, _)| id.clone())
+                .collect::<Vec<_>>();
+            ids.into_iter()
+                .filter_map(|id| sessions.remove(&id))
+                .collect::<Vec<_>>()
+        };
+        self.removed.notify_waiters();
+        for session in deleted {
+            session.delete();
+        }
+    }
+}
+
+/// Keeps a session on the server while its connection attempt, and then its connection, is alive.
+pub(crate) struct SessionGuard {
+    sessions: std::sync::Arc<Sessions>,
+    id: String,
+    session: std::sync::Arc<Session>,
+}
+
+impl SessionGuard {
+    /// Create a new session on the server.
+    pub(crate) fn new(
+        sessions: std::sync::Arc<Sessions>,
+        candidates: Option<crate::trickle::Candidates>,
+    ) -> Self {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let session = std::sync::Arc::new(Session {
+            candidates: candidates.map(std::sync::Arc::new),
+            connection: std::sync::Mutex::new(SessionConnection::Pending),
+            deleted: tokio_util::sync::CancellationToken::new(),
+        });
+        sessions.insert(id.clone(), std::sync::Arc::clone(&session));
+        Self {
+            sessions,
+            id,
+            session,
+        }
+    }
+
+    pub(crate) fn session(&self) -> &std::sync::Arc<Session> {
+        &self.session
+    }
+
+    /// The URL of the session resource, relative to the offer endpoint at the given URI.
+    ///
+    /// The session is nested under the offer endpoint's path, so this is relative to its last segment to stay correct
+    /// wherever the router is mounted.
+    pub(crate) fn location(&self, offer_uri: &axum::http::Uri) -> String {
+        match offer_uri.path().rsplit('/').next() {
+            Some(segment) if !segment.is_empty() => format!("{segment}/sessions/{}", self.id),
+            _ => format!("sessions/{}", self.id),
+        }
+    }
+
+    /// Tie the session to its connection, keeping it until the connection is closed or fails.
+    ///
+    /// If the session has already been deleted, the connection is closed instead.
+    pub(crate) fn attach(
+        self,
+        conn: &dachannel::Connection,
+    ) -> Result<std::sync::Arc<Session>, crate::Error> {
+        let handle = conn.handle();
+        {
+            let mut connection = self.session.connection.lock().unwrap();
+            if let SessionConnection::Deleted = *connection {
+                drop(connection);
+                handle.close()?;
+                return Err(crate::Error::Closed);
+            }
+            *connection = SessionConnection::Connecting(handle.clone());
+        }
+
+        let session = std::sync::Arc::clone(&self.session);
+        let mut connection_state = handle.watch_state();
+        tokio::spawn(async move {
+            connection_state
+                .wait_for(|state| {
+                    matches!(
+                        state,
+                        dachannel::PeerConnectionState::Failed
+                            | dachannel::PeerConnectionState::Closed
+                    )
+                })
+                .await;
+            drop(self);
+        });
+        Ok(session)
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.sessions.remove(&self.id);
+    }
+}
+
+/// Close the session's connection.
+pub(crate) async fn delete_session(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<crate::AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<axum::http::StatusCode, axum::http::StatusCode> {
+    let session = state
+        .sessions
+        .remove(&id)
+        .ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    session.delete();
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,80 @@
+/// A handle for shutting down a server gracefully, from [`crate::ServeOptions::shutdown_handle`].
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    shutting_down: std::sync::Arc<tokio::sync::watch::Sender<bool>>,
+    pub(crate) sessions: std::sync::Arc<crate::session::Sessions>,
+}
+
+impl ShutdownHandle {
+    pub(crate) fn new() -> Self {
+        Self {
+            shutting_down: std::sync::Arc::new(tokio::sync::watch::channel(false).0),
+            sessions: Default::default(),
+        }
+    }
+
+    /// Whether the server has started shutting down.
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutting_down.borrow()
+    }
+
+    /// Wait for the server to start shutting down.
+    pub async fn shutting_down(&self) {
+        let _ = self
+            .shutting_down
+            .subscribe()
+            .wait_for(|shutting_down| *shutting_down)
+            .await;
+    }
+
+    /// Shut down the server.
+    ///
+    /// New offers are refused with `503 Service Unavailable`, and [`crate::ServeOptions::serve`] stops accepting HTTP
+    /// connections and completes once the remaining requests are done. Connections that are still being set up are
+    /// given until the deadline to finish, after which they are refused or closed.
+    ///
+    /// If `close_connections` is set, live connections are also given until the deadline to close, after which they are
+    /// closed. Otherwise, they are left open.
+    pub async fn shutdown(&self, deadline: std::time::Duration, close_connections: bool) {
+        self.shutting_down.send_replace(true);
+
+        let draining =
+            |session: &crate::session::Session| close_connections || session.is_connecting();
+        if tokio::time::timeout(deadline, self.sessions.wait_until_none(draining))
+            .await
+            .is_err()
+        {
+            log::debug!("shutdown deadline passed, closing remaining connections");
+        }
+        self.sessions.delete_all(draining);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[tokio::test]
+    pub async fn test_shutdown() {
+        use futures::StreamExt as _;
+
+        let mut server = crate::test::serve(crate::ServeOptions::new()).await;
+
+        let client_jh = crate::test::connect(
+            dachannel_client::ConnectOptions::new(),
+            dachannel::Connection::builder(Default::default()).unwrap(),
+            &server.url,
+        );
+
+        let connecting = server.connecting_rx.next().await.unwrap();
+        let conn = connecting.await.unwrap();
+        let _client_conn = client_jh.await.unwrap().unwrap();
+
+        server
+            .shutdown_handle
+            .shutdown(std::time::Duration::from_secs(1), true)
+            .await;
+        assert_eq!(conn.state(), dachannel::PeerConnectionState::Closed);
+        assert!(server.shutdown_handle.is_shutting_down());
+
+        server.serve_jh.await.unwrap();
+    }
+}
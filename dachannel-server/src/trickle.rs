@@ -0,0 +1,348 @@
+use futures::FutureExt as _;
+use futures::StreamExt as _;
+use http_body_util::BodyExt as _;
+
+/// The media type of bodies carrying ICE candidates, from RFC 8840.
+const TRICKLE_ICE_SDPFRAG: &str = "application/trickle-ice-sdpfrag";
+
+/// How long a request for local candidates waits for new candidates before returning an empty response.
+const LONG_POLL_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// The HTTP side of candidate exchange for a session.
+pub(crate) struct Candidates {
+    /// The ICE username fragment of the client's offer, which candidates sent by the client must carry.
+    remote_ice_ufrag: std::sync::Arc<std::sync::OnceLock<String>>,
+    remote_candidates_tx: futures::channel::mpsc::UnboundedSender<Option<(String, Option<String>)>>,
+    local_candidates_rx:
+        tokio::sync::Mutex<futures::channel::mpsc::UnboundedReceiver<Option<String>>>,
+}
+
+/// The connection side of candidate exchange for a session.
+pub(crate) struct Trickle {
+    remote_ice_ufrag: std::sync::Arc<std::sync::OnceLock<String>>,
+    remote_candidates_rx:
+        futures::channel::mpsc::UnboundedReceiver<Option<(String, Option<String>)>>,
+    local_candidates_tx: futures::channel::mpsc::UnboundedSender<Option<String>>,
+}
+
+enum TrickleStep {
+    LocalCandidate(Option<String>),
+    LocalCandidatesGathered,
+    RemoteCandidate(Option<(String, Option<String>)>),
+    ConnectionState(dachannel::PeerConnectionState),
+}
+
+/// Create both sides of candidate exchange for a session.
+pub(crate) fn channel() -> (Candidates, Trickle) {
+    let (remote_candidates_tx, remote_candidates_rx) = futures::channel::mpsc::unbounded();
+    let (local_candidates_tx, local_candidates_rx) = futures::channel::mpsc::unbounded();
+    let remote_ice_ufrag = std::sync::Arc::new(std::sync::OnceLock::new());
+    (
+        Candidates {
+            remote_ice_ufrag: std::sync::Arc::clone(&remote_ice_ufrag),
+            remote_candidates_tx,
+            local_candidates_rx: tokio::sync::Mutex::new(local_candidates_rx),
+        },
+        Trickle {
+            remote_ice_ufrag,
+            remote_candidates_rx,
+            local_candidates_tx,
+        },
+    )
+}
+
+impl Trickle {
+    /// Record the ICE username fragment of the client's offer, so that candidates sent by the client can be checked
+    /// against it. This must happen before the answer is sent, since the client only sends candidates after that.
+    pub(crate) fn set_offer(&self, offer_sdp: &str) {
+        if let Some(ice_ufrag) = parse_sdpfrag(offer_sdp).ice_ufrag {
+            let _ = self.remote_ice_ufrag.set(ice_ufrag);
+        }
+    }
+
+    /// Exchange candidates with the client until the connection is connected, advertising host candidates as
+    /// configured.
+    pub(crate) async fn run(
+        mut self,
+        conn: &mut dachannel::Connection,
+        host_candidates: &crate::candidates::HostCandidates,
+    ) -> Result<(), crate::Error> {
+        let mut connection_state = conn.watch_state();
+        let mut ice_gathering_state = conn.watch_ice_gathering_state();
+        let mut local_done = false;
+        let mut remote_done = false;
+
+        loop {
+            let step = futures::select! {
+                cand = async {
+                    if local_done {
+                        futures::future::pending().await
+                    } else {
+                        conn.next_ice_candidate().await
+                    }
+                }.fuse() => TrickleStep::LocalCandidate(cand),
+                _ = async {
+                    if local_done {
+                        futures::future::pending().await
+                    } else {
+                        ice_gathering_state
+                            .wait_for(|state| *state == dachannel::IceGatheringState::Complete)
+                            .await
+                    }
+                }.fuse() => TrickleStep::LocalCandidatesGathered,
+                cand = async {
+                    if remote_done {
+                        futures::future::pending().await
+                    } else {
+                        self.remote_candidates_rx.next().await.flatten()
+                    }
+                }.fuse() => TrickleStep::RemoteCandidate(cand),
+                state = connection_state.wait_for(|state| {
+                    matches!(
+                        state,
+                        dachannel::PeerConnectionState::Connected
+                            | dachannel::PeerConnectionState::Failed
+                            | dachannel::PeerConnectionState::Closed
+                    )
+                }).fuse() => TrickleStep::ConnectionState(state),
+            };
+
+            match step {
+                TrickleStep::LocalCandidate(Some(cand)) => {
+                    self.send_local_candidate(&cand, host_candidates);
+                }
+                TrickleStep::LocalCandidate(None) | TrickleStep::LocalCandidatesGathered => {
+                    while let Some(Some(cand)) = conn.next_ice_candidate().now_or_never() {
+                        self.send_local_candidate(&cand, host_candidates);
+                    }
+                    let _ = self.local_candidates_tx.unbounded_send(None);
+                    local_done = true;
+                }
+                TrickleStep::RemoteCandidate(Some((cand, mid))) => {
+                    if let Err(e) = conn.add_ice_candidate(Some(&cand), mid.as_deref()).await {
+                        log::warn!("failed to add remote candidate {cand:?}: {e}");
+                    }
+                }
+                TrickleStep::RemoteCandidate(None) => {
+                    remote_done = true;
+                }
+                TrickleStep::ConnectionState(dachannel::PeerConnectionState::Failed) => {
+                    return Err(dachannel::Error::ConnectionFailed.into());
+                }
+                TrickleStep::ConnectionState(dachannel::PeerConnectionState::Closed) => {
+                    return Err(dachannel::Error::ConnectionClosed.into());
+                }
+                TrickleStep::ConnectionState(_) => {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn send_local_candidate(
+        &self,
+        cand: &str,
+        host_candidates: &crate::candidates::HostCandidates,
+    ) {
+        for cand in host_candidates.rewrite_candidate(cand) {
+            let _ = self.local_candidates_tx.unbounded_send(Some(cand));
+        }
+    }
+}
+
+/// Get the candidate exchange of a session that is using trickle ICE.
+fn get_candidates_for_session(
+    state: &crate::AppState,
+    id: &str,
+) -> Result<std::sync::Arc<Candidates>, axum::http::StatusCode> {
+    state
+        .sessions
+        .get(id)?
+        .candidates
+        .clone()
+        .ok_or(axum::http::StatusCode::NOT_FOUND)
+}
+
+/// Add remote candidates sent by the client.
+///
+/// The body must carry the ICE username fragment of the client's offer, as RFC 8840 requires. Bodies without one are
+/// rejected with `400 Bad Request`, and bodies with a different one, e.g. from an ICE restart, with `409 Conflict`.
+pub(crate) async fn patch_candidates(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<crate::AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+    req: axum::extract::Request,
+) -> Result<axum::http::StatusCode, axum::http::StatusCode> {
+    let (parts, body) = req.into_parts();
+    if parts
+        .headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        != Some(TRICKLE_ICE_SDPFRAG)
+    {
+        return Err(axum::http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    let session_candidates = get_candidates_for_session(&state, &id)?;
+    let body = String::from_utf8(
+        body.collect()
+            .await
+            .map_err(|_| axum::http::StatusCode::BAD_REQUEST)?
+            .to_bytes()
+            .to_vec(),
+    )
+    .map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
+
+    let sdpfrag = parse_sdpfrag(&body);
+    let Some(ice_ufrag) = sdpfrag.ice_ufrag else {
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    };
+    if session_candidates.remote_ice_ufrag.get() != Some(&ice_ufrag) {
+        return Err(axum::http::StatusCode::CONFLICT);
+    }
+    for cand in sdpfrag.candidates {
+        let _ = session_candidates
+            .remote_candidates_tx
+            .unbounded_send(Some(cand));
+    }
+    if sdpfrag.end {
+        let _ = session_candidates.remote_candidates_tx.unbounded_send(None);
+    }
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
+/// Wait for local candidates and return them to the client.
+///
+/// The response is empty if no candidates were gathered within the long poll timeout or the server started shutting
+/// down first, and ends with `a=end-of-candidates` once all candidates have been returned.
+pub(crate) async fn get_candidates(
+    axum::extract::State(state): axum::extract::State<std::sync::Arc<crate::AppState>>,
+    axum::extract::Path(id): axum::extract::Path<String>,
+) -> Result<impl axum::response::IntoResponse, axum::http::StatusCode> {
+    let session_candidates = get_candidates_for_session(&state, &id)?;
+
+    let wait_for_candidates = async {
+        let mut local_candidates_rx = session_candidates.local_candidates_rx.lock().await;
+        let mut candidates = vec![];
+        let mut end = false;
+        match tokio::time::timeout(LONG_POLL_TIMEOUT, local_candidates_rx.next()).await {
+            Ok(Some(Some(cand))) => {
+                candidates.push(cand);
+                while let Ok(cand) = local_candidates_rx.try_next() {
+                    match cand {
+                        Some(Some(cand)) => candidates.push(cand),
+                        _ => {
+                            end = true;
+                            break;
+                        }
+                    }
+                }
+            }
+            Ok(_) => {
+                end = true;
+            }
+            Err(_) => {}
+        }
+        (candidates, end)
+    };
+
+    // Graceful shutdown waits for outstanding requests, so don't hold it up for the rest of the long poll.
+    let (candidates, end) = tokio::select! {
+        biased;
+        result = wait_for_candidates => result,
+        () = state.shutdown.shutting_down() => (vec![], false),
+    };
+
+    Ok((
+        [(axum::http::header::CONTENT_TYPE, TRICKLE_ICE_SDPFRAG)],
+        format_sdpfrag(&candidates, end),
+    ))
+}
+
+/// Format candidates as an `application/trickle-ice-sdpfrag` body.
+fn format_sdpfrag(candidates: &[String], end: bool) -> String {
+    let mut body = String::new();
+    for cand in candidates {
+        body.push_str("a=");
+        body.push_str(cand.strip_prefix("a=").unwrap_or(cand));
+        body.push_str("\r\n");
+    }
+    if end {
+        body.push_str("a=end-of-candidates\r\n");
+    }
+    body
+}
+
+/// The contents of an `application/trickle-ice-sdpfrag` body.
+#[derive(Debug, PartialEq)]
+struct Sdpfrag {
+    /// The ICE username fragment, if the body names one.
+    ice_ufrag: Option<String>,
+    /// The candidates, each with the mid of the media section it belongs to if the body names one.
+    candidates: Vec<(String, Option<String>)>,
+    /// Whether the body ends the candidates.
+    end: bool,
+}
+
+/// Parse an `application/trickle-ice-sdpfrag` body.
+///
+/// Lines other than the ICE username fragment, media sections, mids, candidates and `a=end-of-candidates` are ignored.
+fn parse_sdpfrag(body: &str) -> Sdpfrag {
+    let mut sdpfrag = Sdpfrag {
+        ice_ufrag: None,
+        candidates: vec![],
+        end: false,
+    };
+    let mut mid = None;
+    for line in body.lines().map(|line| line.trim_end()) {
+        if line.starts_with("m=") {
+            mid = None;
+        } else if let Some(attr) = line.strip_prefix("a=") {
+            if attr.starts_with("candidate:") {
+                sdpfrag.candidates.push((attr.to_string(), mid.clone()));
+            } else if let Some(m) = attr.strip_prefix("mid:") {
+                mid = Some(m.to_string());
+            } else if let Some(ice_ufrag) = attr.strip_prefix("ice-ufrag:") {
+                sdpfrag
+                    .ice_ufrag
+                    .get_or_insert_with(|| ice_ufrag.to_string());
+            } else if attr == "end-of-candidates" {
+                sdpfrag.end = true;
+            }
+        }
+    }
+    sdpfrag
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn test_parse_sdpfrag() {
+        let body = "a=ice-ufrag:abcd\r\n\
+                    a=ice-pwd:efgh\r\n\
+                    m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+                    a=mid:data\r\n\
+                    a=candidate:1 1 UDP 2122317823 192.168.1.2 50000 typ host\r\n\
+                    a=end-of-candidates\r\n";
+        assert_eq!(
+            parse_sdpfrag(body),
+            Sdpfrag {
+                ice_ufrag: Some("abcd".to_string()),
+                candidates: vec![(
+                    "candidate:1 1 UDP 2122317823 192.168.1.2 50000 typ host".to_string(),
+                    Some("data".to_string())
+                )],
+                end: true,
+            }
+        );
+        assert_eq!(
+            parse_sdpfrag(""),
+            Sdpfrag {
+                ice_ufrag: None,
+                candidates: vec![],
+                end: false,
+            }
+        );
+    }
+}
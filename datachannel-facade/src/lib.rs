@@ -115,6 +115,28 @@ pub enum PeerConnectionState {
     Closed,
 }
 
+/// The read-only RTCDataChannel property readyState returns a string which indicates the state of the data channel's
+/// underlying data connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataChannelState {
+    /// The user agent is in the process of creating the underlying data transport. This is the state of a new
+    /// RTCDataChannel after being created by RTCPeerConnection.createDataChannel(), on the peer which started the
+    /// connection process.
+    Connecting,
+
+    /// The underlying data transport has been established and data can be transferred bidirectionally across it. This
+    /// is the default state of a new RTCDataChannel created by the WebRTC layer when the remote peer created the channel
+    /// and delivered it to the site or app in a datachannel event.
+    Open,
+
+    /// The process of closing the underlying data transport has begun. It is no longer possible to queue new messages
+    /// to be sent, but previously queued messages may still be sent or received before entering the "closed" state.
+    Closing,
+
+    /// The underlying data transport has closed, or the attempt to make the connection failed.
+    Closed,
+}
+
 /// The read-only property RTCPeerConnection.iceGatheringState returns a string that describes the connection's ICE
 /// gathering state. This lets you detect, for example, when collection of ICE candidates has finished.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -131,6 +153,37 @@ pub enum IceGatheringState {
     Complete,
 }
 
+/// The read-only signalingState property on the RTCPeerConnection interface returns a string value describing the state
+/// of the signaling process on the local end of the connection while connecting or reconnecting to another peer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignalingState {
+    /// There is no ongoing exchange of offer and answer underway. This may mean that the RTCPeerConnection object is
+    /// new, in which case both the localDescription and remoteDescription are null; it may also mean that negotiation
+    /// is complete and a connection has been established.
+    Stable,
+
+    /// The local peer has called RTCPeerConnection.setLocalDescription(), passing in SDP representing an offer
+    /// (usually created by calling RTCPeerConnection.createOffer()), and the offer has been applied successfully.
+    HaveLocalOffer,
+
+    /// The remote peer has created an offer and used the signaling server to deliver it to the local peer, which has
+    /// set the offer as the remote description by calling RTCPeerConnection.setRemoteDescription().
+    HaveRemoteOffer,
+
+    /// The offer sent by the remote peer has been applied and an answer has been created (usually by calling
+    /// RTCPeerConnection.createAnswer()) and applied by calling RTCPeerConnection.setLocalDescription(). This
+    /// provisional answer describes the supported media formats and so forth, but may not have a complete set of ICE
+    /// candidates included. Further candidates will be delivered separately later.
+    HaveLocalPranswer,
+
+    /// A provisional answer has been received and successfully applied in response to an offer previously sent and
+    /// established by calling setLocalDescription().
+    HaveRemotePranswer,
+
+    /// The RTCPeerConnection has been closed.
+    Closed,
+}
+
 /// A string representing the current ICE transport policy. Possible values are:
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum IceTransportPolicy {
@@ -171,6 +224,35 @@ pub struct Configuration {
     sys: sys::Configuration,
 }
 
+/// A message sent or received over a data channel.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// A message sent as a string. These are delivered to the remote peer's onmessage event handler as a string.
+    Text(String),
+
+    /// A message sent as binary data. These are delivered to the remote peer's onmessage event handler as an
+    /// ArrayBuffer.
+    Binary(Vec<u8>),
+}
+
+impl Message {
+    /// The contents of the message as bytes. For text messages, this is the UTF-8 encoding of the string.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Message::Text(s) => s.as_bytes(),
+            Message::Binary(buf) => buf,
+        }
+    }
+
+    /// Converts the message into its contents as bytes. For text messages, this is the UTF-8 encoding of the string.
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Message::Text(s) => s.into_bytes(),
+            Message::Binary(buf) => buf,
+        }
+    }
+}
+
 /// An underlying platform error.
 #[derive(thiserror::Error, Debug)]
 #[error("{0}")]
@@ -244,8 +326,15 @@ impl PeerConnection {
 
     /// Adds a new remote candidate to the RTCPeerConnection's remote description, which describes the state of the
     /// remote end of the connection.
-    pub async fn add_ice_candidate(&self, cand: Option<&str>) -> Result<(), Error> {
-        self.inner.add_ice_candidate(cand).await
+    ///
+    /// The candidate is associated with the media section identified by `mid` or, if no mid is given, with the first
+    /// media section.
+    pub async fn add_ice_candidate(
+        &self,
+        cand: Option<&str>,
+        mid: Option<&str>,
+    ) -> Result<(), Error> {
+        self.inner.add_ice_candidate(cand, mid).await
     }
 
     /// An icecandidate event is sent to an RTCPeerConnection when:
@@ -292,6 +381,25 @@ impl PeerConnection {
         self.inner.set_on_connection_state_change(cb)
     }
 
+    /// A signalingstatechange event is sent to an RTCPeerConnection to notify it that its signaling state, as indicated
+    /// by the signalingState property, has changed.
+    pub fn set_on_signaling_state_change(
+        &mut self,
+        cb: Option<impl Fn(SignalingState) + Send + Sync + 'static>,
+    ) {
+        self.inner.set_on_signaling_state_change(cb)
+    }
+
+    /// A negotiationneeded event is sent to the RTCPeerConnection when negotiation of the connection through the
+    /// signaling channel is required. This occurs both during the initial setup of the connection as well as any time
+    /// a change to the communication environment requires reconfiguring the connection.
+    ///
+    /// On native, libdatachannel has no such event, so it is synthesized: it is sent once, when the first data channel
+    /// is created, and never for later changes.
+    pub fn set_on_negotiation_needed(&mut self, cb: Option<impl Fn() + Send + Sync + 'static>) {
+        self.inner.set_on_negotiation_needed(cb)
+    }
+
     /// A datachannel event is sent to an RTCPeerConnection instance when an RTCDataChannel has been added to the
     /// connection, as a result of the remote peer calling RTCPeerConnection.createDataChannel().
     pub fn set_on_data_channel(
@@ -356,7 +464,7 @@ impl DataChannel {
 
     /// The WebRTC message event is sent to the onmessage event handler on an RTCDataChannel object when a message has
     /// been received from the remote peer.
-    pub fn set_on_message(&mut self, cb: Option<impl Fn(&[u8]) + Send + Sync + 'static>) {
+    pub fn set_on_message(&mut self, cb: Option<impl Fn(Message) + Send + Sync + 'static>) {
         self.inner.set_on_message(cb)
     }
 
@@ -385,6 +493,57 @@ impl DataChannel {
         self.inner.buffered_amount()
     }
 
+    /// The read-only RTCDataChannel property label returns a string containing a name describing the data channel. These
+    /// labels are not required to be unique.
+    pub fn label(&self) -> Result<String, crate::Error> {
+        self.inner.label()
+    }
+
+    /// The read-only RTCDataChannel property protocol returns a string containing the name of the subprotocol in use.
+    /// If no protocol was specified when the data channel was created, then this property's value is the empty string
+    /// ("").
+    pub fn protocol(&self) -> Result<String, crate::Error> {
+        self.inner.protocol()
+    }
+
+    /// The read-only RTCDataChannel property id returns an ID number (between 0 and 65,534) which uniquely identifies
+    /// the RTCDataChannel.
+    pub fn id(&self) -> Result<Option<u16>, crate::Error> {
+        self.inner.id()
+    }
+
+    /// The read-only RTCDataChannel property readyState returns a string which indicates the state of the data
+    /// channel's underlying data connection.
+    pub fn ready_state(&self) -> Result<DataChannelState, crate::Error> {
+        self.inner.ready_state()
+    }
+
+    /// The read-only RTCDataChannel property ordered indicates whether or not the data channel guarantees in-order
+    /// delivery of messages; the default is true, which indicates that the data channel is indeed ordered.
+    pub fn ordered(&self) -> Result<bool, crate::Error> {
+        self.inner.ordered()
+    }
+
+    /// The read-only RTCDataChannel property maxPacketLifeTime returns the amount of time, in milliseconds, the browser
+    /// is allowed to take to attempt to transmit a message, as set when the data channel was created, or null. This
+    /// limits how long the browser can continue to attempt to transmit and retransmit the message before giving up.
+    pub fn max_packet_life_time(&self) -> Result<Option<u16>, crate::Error> {
+        self.inner.max_packet_life_time()
+    }
+
+    /// The read-only RTCDataChannel property maxRetransmits returns the maximum number of times the browser should try
+    /// to retransmit a message before giving up, as set when the data channel was created, or null, which indicates
+    /// that there is no maximum.
+    pub fn max_retransmits(&self) -> Result<Option<u16>, crate::Error> {
+        self.inner.max_retransmits()
+    }
+
+    /// The maxMessageSize read-only property of the RTCSctpTransport interface indicates the maximum size of a message
+    /// that can be sent using the RTCDataChannel.send() method.
+    pub fn max_message_size(&self) -> Result<usize, crate::Error> {
+        self.inner.max_message_size()
+    }
+
     /// The RTCDataChannel.close() method closes the RTCDataChannel. Either peer is permitted to call this method to
     /// initiate closure of the channel.
     ///
@@ -402,6 +561,11 @@ impl DataChannel {
     pub fn send(&self, buf: &[u8]) -> Result<(), crate::Error> {
         self.inner.send(buf)
     }
+
+    /// Like [`DataChannel::send`], but sends the data as a string rather than as binary data.
+    pub fn send_text(&self, s: &str) -> Result<(), crate::Error> {
+        self.inner.send_text(s)
+    }
 }
 
 #[cfg(test)]
@@ -486,13 +650,13 @@ mod test {
         }));
 
         let (tx1, mut rx1) = futures::channel::mpsc::unbounded();
-        dc1.set_on_message(Some(move |msg: &[u8]| {
-            tx1.unbounded_send(msg.to_vec()).unwrap();
+        dc1.set_on_message(Some(move |msg: Message| {
+            tx1.unbounded_send(msg).unwrap();
         }));
 
         let (tx2, mut rx2) = futures::channel::mpsc::unbounded();
-        dc2.set_on_message(Some(move |msg: &[u8]| {
-            tx2.unbounded_send(msg.to_vec()).unwrap();
+        dc2.set_on_message(Some(move |msg: Message| {
+            tx2.unbounded_send(msg).unwrap();
         }));
 
         pc2.set_remote_description(&pc1.local_description().unwrap().unwrap())
@@ -510,9 +674,21 @@ mod test {
         dc2_open.notified().await;
 
         dc1.send(b"hello world!").unwrap();
-        assert_eq!(rx2.next().await.unwrap(), b"hello world!");
+        assert_eq!(
+            rx2.next().await.unwrap(),
+            Message::Binary(b"hello world!".to_vec())
+        );
 
         dc2.send(b"goodbye world!").unwrap();
-        assert_eq!(rx1.next().await.unwrap(), b"goodbye world!");
+        assert_eq!(
+            rx1.next().await.unwrap(),
+            Message::Binary(b"goodbye world!".to_vec())
+        );
+
+        dc1.send_text("hello text!").unwrap();
+        assert_eq!(
+            rx2.next().await.unwrap(),
+            Message::Text("hello text!".to_string())
+        );
     }
 }
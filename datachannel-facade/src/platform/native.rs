@@ -20,3 +20,28 @@ impl ConfigurationExt for crate::Configuration {
         self.sys.enable_ice_udp_mux = value;
     }
 }
+
+/// Native platform-specific extensions to [`crate::DataChannel`].
+pub trait DataChannelExt {
+    /// Set a callback for when messages become available to receive via [`DataChannelExt::receive`].
+    ///
+    /// The callback is only called when the receive queue goes from empty to non-empty, so all available messages
+    /// should be received before waiting for the next call.
+    fn set_on_available(&mut self, cb: Option<impl Fn() + Send + Sync + 'static>);
+
+    /// Receive the next queued message, or [`None`] if no message is available.
+    ///
+    /// Messages are only queued while no message callback is set. Queued messages count against the SCTP receive
+    /// window, so the remote peer is slowed down until they are received.
+    fn receive(&self) -> Result<Option<crate::Message>, crate::Error>;
+}
+
+impl DataChannelExt for crate::DataChannel {
+    fn set_on_available(&mut self, cb: Option<impl Fn() + Send + Sync + 'static>) {
+        self.inner.set_on_available(cb)
+    }
+
+    fn receive(&self) -> Result<Option<crate::Message>, crate::Error> {
+        self.inner.receive()
+    }
+}
@@ -3,6 +3,8 @@ pub type Configuration = libdatachannel::Configuration;
 pub struct PeerConnection {
     inner: libdatachannel::PeerConnection,
     local_description_set_notify: std::sync::Arc<async_notify::Notify>,
+    on_negotiation_needed: Option<Box<dyn Fn() + Send + Sync>>,
+    has_data_channel: std::sync::atomic::AtomicBool,
 }
 
 impl From<libdatachannel::Error> for crate::Error {
@@ -11,16 +13,6 @@ impl From<libdatachannel::Error> for crate::Error {
     }
 }
 
-#[derive(thiserror::Error, Debug)]
-#[error("{0}")]
-pub struct DataChannelError(String);
-
-impl From<DataChannelError> for crate::Error {
-    fn from(value: DataChannelError) -> Self {
-        Self(value.into())
-    }
-}
-
 impl From<crate::SdpType> for libdatachannel::SdpType {
     fn from(value: crate::SdpType) -> Self {
         match value {
@@ -61,6 +53,15 @@ impl From<libdatachannel::Description> for crate::Description {
     }
 }
 
+impl From<libdatachannel::Message> for crate::Message {
+    fn from(value: libdatachannel::Message) -> Self {
+        match value {
+            libdatachannel::Message::Text(s) => Self::Text(s),
+            libdatachannel::Message::Binary(buf) => Self::Binary(buf),
+        }
+    }
+}
+
 impl From<libdatachannel::GatheringState> for crate::IceGatheringState {
     fn from(value: libdatachannel::GatheringState) -> Self {
         match value {
@@ -83,6 +84,18 @@ impl From<libdatachannel::State> for crate::PeerConnectionState {
     }
 }
 
+impl From<libdatachannel::SignalingState> for crate::SignalingState {
+    fn from(value: libdatachannel::SignalingState) -> Self {
+        match value {
+            libdatachannel::SignalingState::Stable => Self::Stable,
+            libdatachannel::SignalingState::HaveLocalOffer => Self::HaveLocalOffer,
+            libdatachannel::SignalingState::HaveRemoteOffer => Self::HaveRemoteOffer,
+            libdatachannel::SignalingState::HaveLocalPranswer => Self::HaveLocalPranswer,
+            libdatachannel::SignalingState::HaveRemotePranswer => Self::HaveRemotePranswer,
+        }
+    }
+}
+
 impl PeerConnection {
     pub fn new(config: crate::Configuration) -> Result<Self, crate::Error> {
         let local_description_set_notify = std::sync::Arc::new(async_notify::Notify::new());
@@ -138,6 +151,8 @@ impl PeerConnection {
         Ok(Self {
             inner: pc,
             local_description_set_notify,
+            on_negotiation_needed: None,
+            has_data_channel: false.into(),
         })
     }
 
@@ -185,8 +200,12 @@ impl PeerConnection {
         }
     }
 
-    pub async fn add_ice_candidate(&self, cand: Option<&str>) -> Result<(), crate::Error> {
-        self.inner.add_remote_candidate(&cand.unwrap_or(""))?;
+    pub async fn add_ice_candidate(
+        &self,
+        cand: Option<&str>,
+        mid: Option<&str>,
+    ) -> Result<(), crate::Error> {
+        self.inner.add_remote_candidate(cand.unwrap_or(""), mid)?;
         Ok(())
     }
 
@@ -216,6 +235,19 @@ impl PeerConnection {
             .set_on_state_change(cb.map(|cb| move |state: libdatachannel::State| cb(state.into())))
     }
 
+    pub fn set_on_signaling_state_change(
+        &mut self,
+        cb: Option<impl Fn(crate::SignalingState) + Send + Sync + 'static>,
+    ) {
+        self.inner.set_on_signaling_state_change(
+            cb.map(|cb| move |state: libdatachannel::SignalingState| cb(state.into())),
+        )
+    }
+
+    pub fn set_on_negotiation_needed(&mut self, cb: Option<impl Fn() + Send + Sync + 'static>) {
+        self.on_negotiation_needed = cb.map(|cb| Box::new(cb) as _);
+    }
+
     pub fn set_on_data_channel(
         &mut self,
         cb: Option<impl Fn(DataChannel) + Send + Sync + 'static>,
@@ -229,7 +261,7 @@ impl PeerConnection {
         label: &str,
         options: crate::DataChannelOptions,
     ) -> Result<DataChannel, crate::Error> {
-        Ok(DataChannel {
+        let dc = DataChannel {
             inner: self.inner.create_data_channel(
                 label,
                 libdatachannel::DataChannelOptions {
@@ -245,7 +277,18 @@ impl PeerConnection {
                     stream: options.id,
                 },
             )?,
-        })
+        };
+        // Automatic negotiation is disabled, so libdatachannel never asks for negotiation itself. Like browsers, ask for
+        // it when the first data channel is created, as that adds the SCTP transport to the session.
+        if !self
+            .has_data_channel
+            .swap(true, std::sync::atomic::Ordering::SeqCst)
+        {
+            if let Some(cb) = &self.on_negotiation_needed {
+                cb();
+            }
+        }
+        Ok(dc)
     }
 }
 
@@ -267,13 +310,26 @@ impl DataChannel {
     }
 
     pub fn set_on_error(&mut self, cb: Option<impl Fn(crate::Error) + Send + Sync + 'static>) {
-        self.inner.set_on_error(
-            cb.map(|cb| move |err: &str| cb(DataChannelError(err.to_string()).into())),
+        self.inner
+            .set_on_error(cb.map(|cb| move |err: libdatachannel::Error| cb(err.into())));
+    }
+
+    pub fn set_on_message(&mut self, cb: Option<impl Fn(crate::Message) + Send + Sync + 'static>) {
+        self.inner.set_on_message(
+            cb.map(|cb| move |message: libdatachannel::Message| cb(message.into())),
         );
     }
 
-    pub fn set_on_message(&mut self, cb: Option<impl Fn(&[u8]) + Send + Sync + 'static>) {
-        self.inner.set_on_message(cb);
+    pub fn set_on_available(&mut self, cb: Option<impl Fn() + Send + Sync + 'static>) {
+        self.inner.set_on_available(cb);
+    }
+
+    pub fn receive(&self) -> Result<Option<crate::Message>, crate::Error> {
+        match self.inner.receive_message() {
+            Ok(message) => Ok(Some(message.into())),
+            Err(libdatachannel::Error::NotAvail) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
     pub fn set_buffered_amount_low_threshold(&self, value: u32) -> Result<(), crate::Error> {
@@ -286,6 +342,63 @@ impl DataChannel {
         Ok(self.inner.buffered_amount()? as u32)
     }
 
+    pub fn label(&self) -> Result<String, crate::Error> {
+        Ok(self.inner.label()?)
+    }
+
+    pub fn protocol(&self) -> Result<String, crate::Error> {
+        Ok(self.inner.protocol()?)
+    }
+
+    pub fn id(&self) -> Result<Option<u16>, crate::Error> {
+        match self.inner.stream() {
+            Ok(stream) => Ok(Some(stream)),
+            Err(libdatachannel::Error::NotAvail) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn ready_state(&self) -> Result<crate::DataChannelState, crate::Error> {
+        // libdatachannel closes channels immediately, so they are never observed as closing.
+        Ok(if self.inner.is_closed() {
+            crate::DataChannelState::Closed
+        } else if self.inner.is_open() {
+            crate::DataChannelState::Open
+        } else {
+            crate::DataChannelState::Connecting
+        })
+    }
+
+    pub fn ordered(&self) -> Result<bool, crate::Error> {
+        Ok(!self.inner.reliability()?.unordered)
+    }
+
+    pub fn max_packet_life_time(&self) -> Result<Option<u16>, crate::Error> {
+        let reliability = self.inner.reliability()?;
+        Ok(
+            if reliability.unreliable && reliability.max_packet_life_time > 0 {
+                Some(reliability.max_packet_life_time as u16)
+            } else {
+                None
+            },
+        )
+    }
+
+    pub fn max_retransmits(&self) -> Result<Option<u16>, crate::Error> {
+        let reliability = self.inner.reliability()?;
+        Ok(
+            if reliability.unreliable && reliability.max_packet_life_time == 0 {
+                Some(reliability.max_retransmits as u16)
+            } else {
+                None
+            },
+        )
+    }
+
+    pub fn max_message_size(&self) -> Result<usize, crate::Error> {
+        Ok(self.inner.max_message_size()?)
+    }
+
     pub fn close(&self) -> Result<(), crate::Error> {
         self.inner.close()?;
         Ok(())
@@ -295,4 +408,9 @@ impl DataChannel {
         self.inner.send(buf)?;
         Ok(())
     }
+
+    pub fn send_text(&self, s: &str) -> Result<(), crate::Error> {
+        self.inner.send_text(s)?;
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
 pub type Configuration = web_datachannel::Configuration;
 
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 65536;
+
 pub struct PeerConnection {
     inner: web_datachannel::PeerConnection,
 }
@@ -51,13 +53,26 @@ impl From<web_datachannel::Description> for crate::Description {
     }
 }
 
+impl From<web_datachannel::Message> for crate::Message {
+    fn from(value: web_datachannel::Message) -> Self {
+        match value {
+            web_datachannel::Message::Text(s) => Self::Text(s),
+            web_datachannel::Message::Binary(buf) => Self::Binary(buf),
+        }
+    }
+}
+
 impl From<web_datachannel::IceGatheringState> for crate::IceGatheringState {
     fn from(value: web_datachannel::IceGatheringState) -> Self {
         match value {
             web_datachannel::IceGatheringState::New => Self::New,
             web_datachannel::IceGatheringState::Gathering => Self::Gathering,
             web_datachannel::IceGatheringState::Complete => Self::Complete,
-            _ => unreachable!(),
+            // Browsers may report states newer than web-sys knows about, so treat them as still in progress.
+            value => {
+                log::warn!("unknown ICE gathering state: {value:?}");
+                Self::Gathering
+            }
         }
     }
 }
@@ -71,7 +86,42 @@ impl From<web_datachannel::PeerConnectionState> for crate::PeerConnectionState {
             web_datachannel::PeerConnectionState::Disconnected => Self::Disconnected,
             web_datachannel::PeerConnectionState::Failed => Self::Failed,
             web_datachannel::PeerConnectionState::Closed => Self::Closed,
-            _ => unreachable!(),
+            value => {
+                log::warn!("unknown peer connection state: {value:?}");
+                Self::Connecting
+            }
+        }
+    }
+}
+
+impl From<web_datachannel::SignalingState> for crate::SignalingState {
+    fn from(value: web_datachannel::SignalingState) -> Self {
+        match value {
+            web_datachannel::SignalingState::Stable => Self::Stable,
+            web_datachannel::SignalingState::HaveLocalOffer => Self::HaveLocalOffer,
+            web_datachannel::SignalingState::HaveRemoteOffer => Self::HaveRemoteOffer,
+            web_datachannel::SignalingState::HaveLocalPranswer => Self::HaveLocalPranswer,
+            web_datachannel::SignalingState::HaveRemotePranswer => Self::HaveRemotePranswer,
+            web_datachannel::SignalingState::Closed => Self::Closed,
+            value => {
+                log::warn!("unknown signaling state: {value:?}");
+                Self::Stable
+            }
+        }
+    }
+}
+
+impl From<web_datachannel::DataChannelState> for crate::DataChannelState {
+    fn from(value: web_datachannel::DataChannelState) -> Self {
+        match value {
+            web_datachannel::DataChannelState::Connecting => Self::Connecting,
+            web_datachannel::DataChannelState::Open => Self::Open,
+            web_datachannel::DataChannelState::Closing => Self::Closing,
+            web_datachannel::DataChannelState::Closed => Self::Closed,
+            value => {
+                log::warn!("unknown data channel state: {value:?}");
+                Self::Connecting
+            }
         }
     }
 }
@@ -131,12 +181,17 @@ impl PeerConnection {
         Ok(self.inner.remote_description().map(|v| v.into()))
     }
 
-    pub async fn add_ice_candidate(&self, cand: Option<&str>) -> Result<(), crate::Error> {
+    pub async fn add_ice_candidate(
+        &self,
+        cand: Option<&str>,
+        mid: Option<&str>,
+    ) -> Result<(), crate::Error> {
         self.inner
             .add_ice_candidate(
                 cand.map(|cand| cand.to_string())
                     .as_ref()
                     .map(|v| v.as_str()),
+                mid,
             )
             .await?;
         Ok(())
@@ -167,6 +222,19 @@ impl PeerConnection {
         );
     }
 
+    pub fn set_on_signaling_state_change(
+        &mut self,
+        cb: Option<impl Fn(crate::SignalingState) + Send + Sync + 'static>,
+    ) {
+        self.inner.set_on_signaling_state_change(
+            cb.map(|cb| move |state: web_datachannel::SignalingState| cb(state.into())),
+        );
+    }
+
+    pub fn set_on_negotiation_needed(&mut self, cb: Option<impl Fn() + Send + Sync + 'static>) {
+        self.inner.set_on_negotiation_needed(cb);
+    }
+
     pub fn set_on_data_channel(
         &mut self,
         cb: Option<impl Fn(DataChannel) + Send + Sync + 'static>,
@@ -219,8 +287,10 @@ impl DataChannel {
             .set_on_error(cb.map(|cb| move |err: web_datachannel::Error| cb(err.into())));
     }
 
-    pub fn set_on_message(&mut self, cb: Option<impl Fn(&[u8]) + Send + Sync + 'static>) {
-        self.inner.set_on_message(cb);
+    pub fn set_on_message(&mut self, cb: Option<impl Fn(crate::Message) + Send + Sync + 'static>) {
+        self.inner.set_on_message(
+            cb.map(|cb| move |message: web_datachannel::Message| cb(message.into())),
+        );
     }
 
     pub fn set_buffered_amount_low_threshold(&self, value: u32) -> Result<(), crate::Error> {
@@ -232,6 +302,44 @@ impl DataChannel {
         Ok(self.inner.buffered_amount())
     }
 
+    pub fn label(&self) -> Result<String, crate::Error> {
+        Ok(self.inner.label())
+    }
+
+    pub fn protocol(&self) -> Result<String, crate::Error> {
+        Ok(self.inner.protocol())
+    }
+
+    pub fn id(&self) -> Result<Option<u16>, crate::Error> {
+        Ok(self.inner.id())
+    }
+
+    pub fn ready_state(&self) -> Result<crate::DataChannelState, crate::Error> {
+        Ok(self.inner.ready_state().into())
+    }
+
+    pub fn ordered(&self) -> Result<bool, crate::Error> {
+        Ok(self.inner.ordered())
+    }
+
+    pub fn max_packet_life_time(&self) -> Result<Option<u16>, crate::Error> {
+        Ok(self.inner.max_packet_life_time())
+    }
+
+    pub fn max_retransmits(&self) -> Result<Option<u16>, crate::Error> {
+        Ok(self.inner.max_retransmits())
+    }
+
+    pub fn max_message_size(&self) -> Result<usize, crate::Error> {
+        // Before the SCTP transport is established the browser does not know the remote maximum, so assume the same
+        // conservative default that libdatachannel does.
+        Ok(self
+            .inner
+            .max_message_size()
+            .map(|v| v as usize)
+            .unwrap_or(DEFAULT_MAX_MESSAGE_SIZE))
+    }
+
     pub fn close(&self) -> Result<(), crate::Error> {
         self.inner.close();
         Ok(())
@@ -241,4 +349,9 @@ impl DataChannel {
         self.inner.send(buf)?;
         Ok(())
     }
+
+    pub fn send_text(&self, s: &str) -> Result<(), crate::Error> {
+        self.inner.send_text(s)?;
+        Ok(())
+    }
 }
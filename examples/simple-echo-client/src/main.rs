@@ -38,10 +38,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
             break;
         }
 
-        dc.send(line.as_bytes()).await?;
+        dc.send_text(&line).await?;
         println!(
             "got: {:?}",
-            String::from_utf8_lossy(&dc.recv().await.unwrap())
+            String::from_utf8_lossy(dc.recv().await?.as_bytes())
         );
     }
 
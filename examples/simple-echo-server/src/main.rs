@@ -59,16 +59,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         tokio::spawn(async move {
             let _conn = conn;
             loop {
-                let buf = match dc.recv().await {
-                    Ok(buf) => buf,
+                let message = match dc.recv().await {
+                    Ok(message) => message,
                     Err(e) => {
                         println!("[{}] disconnected: {}", remote_addr, e);
                         break;
                     }
                 };
 
-                println!("[{}] {:?}", remote_addr, String::from_utf8_lossy(&buf));
-                dc.send(&buf).await.unwrap();
+                println!(
+                    "[{}] {:?}",
+                    remote_addr,
+                    String::from_utf8_lossy(message.as_bytes())
+                );
+                dc.send(message).await.unwrap();
             }
         });
     }
@@ -17,10 +17,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let _conn = dachannel_client::ConnectOptions::new()
         .connect(cb, "http://127.0.0.1:12345")
         .await?;
-    dc.send(b"hello world!!").await?;
+    dc.send_binary(b"hello world!!").await?;
     log::info!(
         "got: {:?}",
-        String::from_utf8_lossy(&dc.recv().await.unwrap())
+        String::from_utf8_lossy(dc.recv().await?.as_bytes())
     );
     Ok(())
 }
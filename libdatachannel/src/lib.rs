@@ -2,19 +2,34 @@ use std::str::FromStr as _;
 
 use num_traits::FromPrimitive as _;
 
-#[derive(thiserror::Error, num_derive::FromPrimitive, Debug)]
+#[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("invalid argument")]
-    Invalid = -1,
+    Invalid,
 
     #[error("runtime error")]
-    Failure = -2,
+    Failure,
 
     #[error("element not available")]
-    NotAvail = -3,
+    NotAvail,
 
     #[error("buffer too small")]
-    TooSmall = -4,
+    TooSmall,
+
+    /// An error reported to a data channel's error callback, with libdatachannel's message.
+    #[error("{0}")]
+    Channel(String),
+}
+
+impl Error {
+    fn from_code(r: i32) -> Self {
+        match r {
+            libdatachannel_sys::RTC_ERR_INVALID => Error::Invalid,
+            libdatachannel_sys::RTC_ERR_NOT_AVAIL => Error::NotAvail,
+            libdatachannel_sys::RTC_ERR_TOO_SMALL => Error::TooSmall,
+            _ => Error::Failure,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
@@ -85,7 +100,7 @@ fn get_string(f: impl Fn(*mut i8, i32) -> i32) -> Result<std::ffi::CString, Erro
 
 fn check_error(r: i32) -> Result<i32, Error> {
     if r < 0 {
-        return Err(Error::from_i32(r as i32).unwrap_or(Error::Failure));
+        return Err(Error::from_code(r));
     }
     Ok(r)
 }
@@ -136,12 +151,23 @@ pub enum GatheringState {
     Complete = libdatachannel_sys::rtcGatheringState_RTC_GATHERING_COMPLETE,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, num_derive::FromPrimitive)]
+#[repr(u32)]
+pub enum SignalingState {
+    Stable = libdatachannel_sys::rtcSignalingState_RTC_SIGNALING_STABLE,
+    HaveLocalOffer = libdatachannel_sys::rtcSignalingState_RTC_SIGNALING_HAVE_LOCAL_OFFER,
+    HaveRemoteOffer = libdatachannel_sys::rtcSignalingState_RTC_SIGNALING_HAVE_REMOTE_OFFER,
+    HaveLocalPranswer = libdatachannel_sys::rtcSignalingState_RTC_SIGNALING_HAVE_LOCAL_PRANSWER,
+    HaveRemotePranswer = libdatachannel_sys::rtcSignalingState_RTC_SIGNALING_HAVE_REMOTE_PRANSWER,
+}
+
 #[derive(Default)]
 struct PeerConnectionUserData {
     on_local_description: Option<Box<dyn Fn(&str, SdpType)>>,
     on_local_candidate: Option<Box<dyn Fn(&str)>>,
     on_state_change: Option<Box<dyn Fn(State)>>,
     on_gathering_state_change: Option<Box<dyn Fn(GatheringState)>>,
+    on_signaling_state_change: Option<Box<dyn Fn(SignalingState)>>,
     on_data_channel: Option<Box<dyn Fn(DataChannel)>>,
 }
 
@@ -303,6 +329,23 @@ impl PeerConnection {
             )
         };
 
+        unsafe {
+            extern "C" fn signaling_state_change_callback(
+                _id: i32,
+                state: libdatachannel_sys::rtcSignalingState,
+                userdata: *mut std::ffi::c_void,
+            ) {
+                let ud = unsafe { &*(userdata as *mut PeerConnectionUserData) };
+                if let Some(cb) = &ud.on_signaling_state_change {
+                    cb(SignalingState::from_u32(state).unwrap());
+                }
+            }
+            libdatachannel_sys::rtcSetSignalingStateChangeCallback(
+                id,
+                Some(signaling_state_change_callback),
+            )
+        };
+
         unsafe {
             extern "C" fn data_channel_callback(
                 _id: i32,
@@ -355,10 +398,17 @@ impl PeerConnection {
         Ok(())
     }
 
-    pub fn add_remote_candidate(&self, cand: &str) -> Result<(), Error> {
+    pub fn add_remote_candidate(&self, cand: &str, mid: Option<&str>) -> Result<(), Error> {
         let raw_cand = std::ffi::CString::new(cand).unwrap();
+        let raw_mid = mid.map(|mid| std::ffi::CString::new(mid).unwrap());
         check_error(unsafe {
-            libdatachannel_sys::rtcAddRemoteCandidate(self.id, raw_cand.as_ptr(), std::ptr::null())
+            libdatachannel_sys::rtcAddRemoteCandidate(
+                self.id,
+                raw_cand.as_ptr(),
+                raw_mid
+                    .as_ref()
+                    .map_or(std::ptr::null(), |mid| mid.as_ptr()),
+            )
         })?;
         Ok(())
     }
@@ -486,6 +536,13 @@ impl PeerConnection {
         self.userdata.on_gathering_state_change = cb.map(|f| Box::new(f) as _);
     }
 
+    pub fn set_on_signaling_state_change(
+        &mut self,
+        cb: Option<impl Fn(SignalingState) + Send + Sync + 'static>,
+    ) {
+        self.userdata.on_signaling_state_change = cb.map(|f| Box::new(f) as _);
+    }
+
     pub fn set_on_data_channel(
         &mut self,
         cb: Option<impl Fn(DataChannel) + Send + Sync + 'static>,
@@ -511,8 +568,8 @@ pub struct DataChannel {
 struct DataChannelUserData {
     on_open: Option<Box<dyn Fn()>>,
     on_closed: Option<Box<dyn Fn()>>,
-    on_error: Option<Box<dyn Fn(&str)>>,
-    on_message: Option<Box<dyn Fn(&[u8])>>,
+    on_error: Option<Box<dyn Fn(Error)>>,
+    on_message: Option<Box<dyn Fn(Message)>>,
     on_buffered_amount_low: Option<Box<dyn Fn()>>,
     on_available: Option<Box<dyn Fn()>>,
 }
@@ -552,27 +609,16 @@ impl DataChannel {
             ) {
                 let ud = unsafe { &*(userdata as *mut DataChannelUserData) };
                 if let Some(cb) = &ud.on_error {
-                    cb(&unsafe { std::ffi::CStr::from_ptr(error) }.to_str().unwrap());
+                    cb(Error::Channel(
+                        unsafe { std::ffi::CStr::from_ptr(error) }
+                            .to_string_lossy()
+                            .into_owned(),
+                    ));
                 }
             }
             libdatachannel_sys::rtcSetErrorCallback(id, Some(error_callback))
         };
 
-        unsafe {
-            extern "C" fn message_callback(
-                _id: i32,
-                message: *const std::ffi::c_char,
-                size: i32,
-                userdata: *mut std::ffi::c_void,
-            ) {
-                let ud = unsafe { &*(userdata as *mut DataChannelUserData) };
-                if let Some(cb) = &ud.on_message {
-                    cb(unsafe { std::slice::from_raw_parts(message as *const _, size as usize) });
-                }
-            }
-            libdatachannel_sys::rtcSetMessageCallback(id, Some(message_callback))
-        };
-
         unsafe {
             extern "C" fn buffered_amount_low(_id: i32, userdata: *mut std::ffi::c_void) {
                 let ud = unsafe { &*(userdata as *mut DataChannelUserData) };
@@ -610,6 +656,19 @@ impl DataChannel {
         Ok(())
     }
 
+    pub fn send_text(&self, s: &str) -> Result<(), Error> {
+        let raw_s = std::ffi::CString::new(s).map_err(|_| Error::Invalid)?;
+        check_error(unsafe { libdatachannel_sys::rtcSendMessage(self.id, raw_s.as_ptr(), -1) })?;
+        Ok(())
+    }
+
+    pub fn send_message(&self, message: &Message) -> Result<(), Error> {
+        match message {
+            Message::Text(s) => self.send_text(s),
+            Message::Binary(buf) => self.send(buf),
+        }
+    }
+
     pub fn close(&self) -> Result<(), Error> {
         check_error(unsafe { libdatachannel_sys::rtcClose(self.id) })?;
         Ok(())
@@ -627,6 +686,47 @@ impl DataChannel {
         Ok(check_error(unsafe { libdatachannel_sys::rtcMaxMessageSize(self.id) })? as usize)
     }
 
+    pub fn label(&self) -> Result<String, Error> {
+        Ok(get_string(|buf, n| unsafe {
+            libdatachannel_sys::rtcGetDataChannelLabel(self.id, buf, n)
+        })?
+        .to_string_lossy()
+        .into_owned())
+    }
+
+    pub fn protocol(&self) -> Result<String, Error> {
+        Ok(get_string(|buf, n| unsafe {
+            libdatachannel_sys::rtcGetDataChannelProtocol(self.id, buf, n)
+        })?
+        .to_string_lossy()
+        .into_owned())
+    }
+
+    pub fn stream(&self) -> Result<u16, Error> {
+        Ok(check_error(unsafe { libdatachannel_sys::rtcGetDataChannelStream(self.id) })? as u16)
+    }
+
+    pub fn reliability(&self) -> Result<Reliability, Error> {
+        let mut raw_reliability = libdatachannel_sys::rtcReliability {
+            unordered: false,
+            unreliable: false,
+            maxPacketLifeTime: 0,
+            maxRetransmits: 0,
+        };
+        check_error(unsafe {
+            libdatachannel_sys::rtcGetDataChannelReliability(
+                self.id,
+                &mut raw_reliability as *mut _,
+            )
+        })?;
+        Ok(Reliability {
+            unordered: raw_reliability.unordered,
+            unreliable: raw_reliability.unreliable,
+            max_packet_life_time: raw_reliability.maxPacketLifeTime,
+            max_retransmits: raw_reliability.maxRetransmits,
+        })
+    }
+
     pub fn buffered_amount(&self) -> Result<usize, Error> {
         Ok(check_error(unsafe { libdatachannel_sys::rtcGetBufferedAmount(self.id) })? as usize)
     }
@@ -642,6 +742,46 @@ impl DataChannel {
         Ok(check_error(unsafe { libdatachannel_sys::rtcGetAvailableAmount(self.id) })? as usize)
     }
 
+    /// The returned size is the buffer size required to receive the message, which includes a NUL terminator for text
+    /// messages.
+    pub fn next_message_size(&self) -> Result<(MessageType, usize), Error> {
+        let mut size = 0;
+        check_error(unsafe {
+            libdatachannel_sys::rtcReceiveMessage(self.id, std::ptr::null_mut(), &mut size)
+        })?;
+        Ok(if size < 0 {
+            (MessageType::Text, size.unsigned_abs() as usize)
+        } else {
+            (MessageType::Binary, size as usize)
+        })
+    }
+
+    /// Messages are only queued for receiving while no message callback is set.
+    ///
+    /// The returned size is the length of the message, excluding the NUL terminator written for text messages.
+    pub fn receive(&self, buf: &mut [u8]) -> Result<(MessageType, usize), Error> {
+        let mut size = buf.len() as i32;
+        check_error(unsafe {
+            libdatachannel_sys::rtcReceiveMessage(self.id, buf.as_mut_ptr() as *mut _, &mut size)
+        })?;
+        Ok(if size < 0 {
+            (MessageType::Text, size.unsigned_abs() as usize - 1)
+        } else {
+            (MessageType::Binary, size as usize)
+        })
+    }
+
+    pub fn receive_message(&self) -> Result<Message, Error> {
+        let (_, size) = self.next_message_size()?;
+        let mut buf = vec![0u8; size];
+        let (type_, n) = self.receive(&mut buf)?;
+        buf.truncate(n);
+        Ok(match type_ {
+            MessageType::Text => Message::Text(String::from_utf8_lossy(&buf).into_owned()),
+            MessageType::Binary => Message::Binary(buf),
+        })
+    }
+
     pub fn set_on_open(&mut self, cb: Option<impl Fn() + Send + Sync + 'static>) {
         self.userdata.on_open = cb.map(|f| Box::new(f) as _);
     }
@@ -654,12 +794,50 @@ impl DataChannel {
         self.userdata.on_buffered_amount_low = cb.map(|f| Box::new(f) as _);
     }
 
-    pub fn set_on_error(&mut self, cb: Option<impl Fn(&str) + Send + Sync + 'static>) {
+    pub fn set_on_error(&mut self, cb: Option<impl Fn(Error) + Send + Sync + 'static>) {
         self.userdata.on_error = cb.map(|f| Box::new(f) as _);
     }
 
-    pub fn set_on_message(&mut self, cb: Option<impl Fn(&[u8]) + Send + Sync + 'static>) {
+    pub fn set_on_message(&mut self, cb: Option<impl Fn(Message) + Send + Sync + 'static>) {
+        extern "C" fn message_callback(
+            _id: i32,
+            message: *const std::ffi::c_char,
+            size: i32,
+            userdata: *mut std::ffi::c_void,
+        ) {
+            let ud = unsafe { &*(userdata as *mut DataChannelUserData) };
+            if let Some(cb) = &ud.on_message {
+                cb(if size < 0 {
+                    Message::Text(
+                        unsafe { std::ffi::CStr::from_ptr(message) }
+                            .to_string_lossy()
+                            .into_owned(),
+                    )
+                } else {
+                    Message::Binary(
+                        unsafe { std::slice::from_raw_parts(message as *const u8, size as usize) }
+                            .to_vec(),
+                    )
+                });
+            }
+        }
+
+        let is_some = cb.is_some();
         self.userdata.on_message = cb.map(|f| Box::new(f) as _);
+        unsafe {
+            libdatachannel_sys::rtcSetMessageCallback(
+                self.id,
+                if is_some {
+                    Some(message_callback)
+                } else {
+                    None
+                },
+            )
+        };
+    }
+
+    pub fn set_on_available(&mut self, cb: Option<impl Fn() + Send + Sync + 'static>) {
+        self.userdata.on_available = cb.map(|f| Box::new(f) as _);
     }
 }
 
@@ -671,7 +849,19 @@ impl Drop for DataChannel {
     }
 }
 
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageType {
+    Text,
+    Binary,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Reliability {
     pub unordered: bool,
     pub unreliable: bool,
@@ -776,13 +966,13 @@ mod tests {
         }));
 
         let (tx1, rx1) = std::sync::mpsc::sync_channel(0);
-        dc1.set_on_message(Some(move |msg: &[u8]| {
-            tx1.send(msg.to_vec()).unwrap();
+        dc1.set_on_message(Some(move |msg: Message| {
+            tx1.send(msg).unwrap();
         }));
 
         let (tx2, rx2) = std::sync::mpsc::sync_channel(0);
-        dc2.set_on_message(Some(move |msg: &[u8]| {
-            tx2.send(msg.to_vec()).unwrap();
+        dc2.set_on_message(Some(move |msg: Message| {
+            tx2.send(msg).unwrap();
         }));
 
         pc2.set_remote_description(&pc1.local_description().unwrap())
@@ -805,9 +995,21 @@ mod tests {
             .unwrap();
 
         dc1.send(b"hello world!").unwrap();
-        assert_eq!(rx2.recv().unwrap(), b"hello world!");
+        assert_eq!(
+            rx2.recv().unwrap(),
+            Message::Binary(b"hello world!".to_vec())
+        );
 
         dc2.send(b"goodbye world!").unwrap();
-        assert_eq!(rx1.recv().unwrap(), b"goodbye world!");
+        assert_eq!(
+            rx1.recv().unwrap(),
+            Message::Binary(b"goodbye world!".to_vec())
+        );
+
+        dc1.send_text("hello text!").unwrap();
+        assert_eq!(
+            rx2.recv().unwrap(),
+            Message::Text("hello text!".to_string())
+        );
     }
 }
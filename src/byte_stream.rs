@@ -0,0 +1,234 @@
+/// A byte stream over a reliable, ordered channel.
+///
+/// Writes are split into datagrams no larger than the channel's maximum message size, and reads are served from
+/// received datagrams regardless of their boundaries. Shutting down the stream closes the channel once all buffered
+/// data has been sent.
+pub struct ByteStream {
+    receiver: crate::Receiver,
+    sender: crate::Sender,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    write_listener: Option<event_listener::EventListener>,
+    flushing: Option<crate::channel::Flushing>,
+    closing: bool,
+}
+
+impl ByteStream {
+    pub(crate) fn new(channel: crate::Channel) -> Self {
+        let (sender, receiver) = channel.split();
+        Self {
+            receiver,
+            sender,
+            read_buf: vec![],
+            read_pos: 0,
+            write_listener: None,
+            flushing: None,
+            closing: false,
+        }
+    }
+
+    fn poll_read_into(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<Result<usize, std::io::Error>> {
+        if buf.is_empty() {
+            return std::task::Poll::Ready(Ok(0));
+        }
+
+        while self.read_pos == self.read_buf.len() {
+            match futures::ready!(self.receiver.poll_recv(cx)) {
+                Some(Ok(message)) => {
+                    self.read_buf = message.into_bytes();
+                    self.read_pos = 0;
+                }
+                Some(Err(e)) => return std::task::Poll::Ready(Err(e.into())),
+                None => return std::task::Poll::Ready(Ok(0)),
+            }
+        }
+
+        let n = std::cmp::min(buf.len(), self.read_buf.len() - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+        std::task::Poll::Ready(Ok(n))
+    }
+
+    fn poll_write_from(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<Result<usize, std::io::Error>> {
+        if self.closing {
+            return std::task::Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "stream shut down",
+            )));
+        }
+        if buf.is_empty() {
+            return std::task::Poll::Ready(Ok(0));
+        }
+
+        futures::ready!(self.sender.poll_writable(cx, &mut self.write_listener))?;
+        let n = std::cmp::min(buf.len(), self.sender.max_message_size()?);
+        self.sender.send_binary_now(&buf[..n])?;
+        std::task::Poll::Ready(Ok(n))
+    }
+
+    fn poll_shutdown_inner(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), std::io::Error>> {
+        self.sender
+            .poll_shutdown(
+                cx,
+                &mut self.write_listener,
+                &mut self.flushing,
+                &mut self.closing,
+            )
+            .map_err(Into::into)
+    }
+}
+
+impl futures::io::AsyncRead for ByteStream {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<Result<usize, std::io::Error>> {
+        self.get_mut().poll_read_into(cx, buf)
+    }
+}
+
+impl futures::io::AsyncWrite for ByteStream {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<Result<usize, std::io::Error>> {
+        self.get_mut().poll_write_from(cx, buf)
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), std::io::Error>> {
+        // Every write is handed to the channel immediately, so there is nothing to flush.
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), std::io::Error>> {
+        self.get_mut().poll_shutdown_inner(cx)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncRead for ByteStream {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> std::task::Poll<Result<(), std::io::Error>> {
+        let n = futures::ready!(self.get_mut().poll_read_into(cx, buf.initialize_unfilled()))?;
+        buf.advance(n);
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncWrite for ByteStream {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<Result<usize, std::io::Error>> {
+        self.get_mut().poll_write_from(cx, buf)
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), std::io::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), std::io::Error>> {
+        self.get_mut().poll_shutdown_inner(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_byte_stream() {
+        use futures::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        let (_conn1, chan1, _conn2, chan2) = crate::test::negotiated_channel_pair().await;
+        let mut stream1 = chan1.into_stream().unwrap();
+        let mut stream2 = chan2.into_stream().unwrap();
+
+        let buf = (0..200_000).map(|i| i as u8).collect::<Vec<_>>();
+        stream1.write_all(&buf).await.unwrap();
+        stream1.close().await.unwrap();
+
+        let mut received = vec![];
+        stream2.read_to_end(&mut received).await.unwrap();
+        assert_eq!(received, buf);
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_byte_stream_unordered() {
+        let cb = crate::Connection::builder(Default::default()).unwrap();
+        let chan = cb
+            .create_data_channel(
+                "test",
+                crate::DataChannelOptions {
+                    ordered: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        let _conn = cb.build();
+        assert!(matches!(
+            chan.into_stream().err().unwrap(),
+            crate::Error::NotReliableOrdered
+        ));
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_byte_stream_message_boundaries() {
+        use futures::{AsyncReadExt as _, AsyncWriteExt as _};
+
+        let (_conn1, chan1, _conn2, chan2) = crate::test::negotiated_channel_pair().await;
+        let mut stream2 = chan2.into_stream().unwrap();
+
+        // Reads are served across datagrams, whatever their boundaries.
+        chan1.send_binary(b"hello ").await.unwrap();
+        chan1.send_text("world").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream2.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hell");
+        let mut buf = [0u8; 7];
+        stream2.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"o world");
+        assert_eq!(stream2.read(&mut []).await.unwrap(), 0);
+
+        let mut stream1 = chan1.into_stream().unwrap();
+        stream1.close().await.unwrap();
+        assert_eq!(
+            stream1.write(b"too late").await.unwrap_err().kind(),
+            std::io::ErrorKind::BrokenPipe
+        );
+        let mut rest = vec![];
+        stream2.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+}
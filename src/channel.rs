@@ -1,21 +1,222 @@
-use futures::StreamExt as _;
+pub use crate::recv_buffer::OverflowPolicy;
+pub use datachannel_facade::DataChannelState;
+pub use datachannel_facade::Message;
+
+use crate::Error;
+
+/// The default number of buffered outgoing bytes above which [`Sender::send`] will wait for the buffer to drain.
+pub const DEFAULT_BUFFERED_AMOUNT_HIGH_THRESHOLD: u32 = 1024 * 1024;
+
+/// Why a channel was closed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CloseReason {
+    /// The channel was closed locally, with [`Channel::close`] or [`Sender::close`].
+    Local,
+
+    /// The channel was closed by the remote peer, or its peer connection was closed.
+    Remote,
+
+    /// The peer connection failed.
+    ConnectionFailed,
+
+    /// The channel was closed after an error, with the error's message.
+    Error(String),
+}
+
+/// State shared between the halves of a channel and its callbacks.
+struct ChannelState {
+    is_open_notify: crate::sync_util::PermanentNotify,
+    is_closed_notify: crate::sync_util::PermanentNotify,
+    connection_failed_notify: std::sync::Arc<crate::sync_util::PermanentNotify>,
+    writable_event: event_listener::Event,
+    buffered_amount_high_threshold: std::sync::atomic::AtomicU32,
+    closing_locally: std::sync::atomic::AtomicBool,
+    /// The number of flushes in progress, during which the buffered amount low threshold is lowered to 0.
+    flushing: std::sync::atomic::AtomicUsize,
+    overflowed: std::sync::atomic::AtomicBool,
+    /// Held while pulling messages into the receive buffer, which happens both from the receiver and from the channel's
+    /// callbacks, so that messages are pulled whole and pushed in order.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pulling: std::sync::Mutex<()>,
+    error: std::sync::Mutex<Option<String>>,
+    close_reason: std::sync::Mutex<Option<CloseReason>>,
+}
+
+impl ChannelState {
+    fn set_error(&self, error: String) {
+        self.error.lock().unwrap().get_or_insert(error);
+        self.writable_event.notify(usize::MAX);
+    }
+
+    /// Whether the channel has closed, or is closing after an error, so nothing more can be sent on it.
+    fn is_done(&self) -> bool {
+        self.is_closed_notify.is_notified() || self.error.lock().unwrap().is_some()
+    }
+
+    fn set_closed(&self) {
+        let reason = if self
+            .closing_locally
+            .load(std::sync::atomic::Ordering::SeqCst)
+        {
+            CloseReason::Local
+        } else if self.connection_failed_notify.is_notified() {
+            CloseReason::ConnectionFailed
+        } else if let Some(error) = self.error.lock().unwrap().clone() {
+            CloseReason::Error(error)
+        } else {
+            CloseReason::Remote
+        };
+        self.close_reason.lock().unwrap().get_or_insert(reason);
+        self.is_closed_notify.notify();
+        self.writable_event.notify(usize::MAX);
+    }
+
+    /// Close the channel with an error if its receive buffer has overflowed with [`OverflowPolicy::Close`].
+    fn close_if_overflowed(
+        &self,
+        buffer: &crate::recv_buffer::RecvBuffer,
+        dc: &datachannel_facade::DataChannel,
+    ) {
+        if !buffer.overflowed()
+            || self
+                .overflowed
+                .swap(true, std::sync::atomic::Ordering::SeqCst)
+        {
+            return;
+        }
+        self.set_error(Error::RecvBufferOverflowed.to_string());
+        if let Err(e) = dc.close() {
+            log::warn!("failed to close overflowed channel: {e}");
+        }
+    }
+
+    async fn closed(&self) -> CloseReason {
+        self.is_closed_notify.notified().await;
+        self.close_reason.lock().unwrap().clone().unwrap()
+    }
+
+    fn closed_error(&self) -> Error {
+        if *self.close_reason.lock().unwrap() == Some(CloseReason::ConnectionFailed) {
+            Error::ConnectionFailed
+        } else {
+            Error::Closed
+        }
+    }
+}
 
 /// The receiver half of a channel.
+///
+/// Received datagrams can also be consumed as a [`futures::Stream`].
 pub struct Receiver {
-    rx: futures::channel::mpsc::UnboundedReceiver<Result<Vec<u8>, datachannel_facade::Error>>,
+    buffer: std::sync::Arc<crate::recv_buffer::RecvBuffer>,
+    listener: Option<event_listener::EventListener>,
+    state: std::sync::Arc<ChannelState>,
+    dc: std::sync::Arc<datachannel_facade::DataChannel>,
 }
 
 impl Receiver {
-    /// Receive a datagram from the channel, or [`None`] if the channel is closed.
-    pub async fn recv(&mut self) -> Result<Vec<u8>, std::io::Error> {
-        Ok(self
-            .rx
-            .next()
+    /// Receive a datagram from the channel.
+    ///
+    /// Once the channel is closed and all received datagrams have been consumed, this returns [`Error::Closed`], or
+    /// [`Error::ConnectionFailed`] if the channel was closed because the peer connection failed.
+    pub async fn recv(&mut self) -> Result<Message, Error> {
+        std::future::poll_fn(|cx| self.poll_recv(cx))
             .await
-            .ok_or_else(|| {
-                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "receiver closed")
-            })?
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?)
+            .unwrap_or_else(|| Err(self.state.closed_error()))
+    }
+
+    /// Wait for the channel to close, returning why it was closed.
+    pub async fn closed(&self) -> CloseReason {
+        self.state.closed().await
+    }
+
+    /// Poll for the next datagram, or [`None`] if the channel is closed.
+    pub(crate) fn poll_recv(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Result<Message, Error>>> {
+        let (dc, state) = (&self.dc, &self.state);
+        self.buffer.poll_pop(cx, &mut self.listener, |buffer| {
+            Self::pull(dc, buffer, state)
+        })
+    }
+
+    /// Pull available messages into the buffer, closing the channel if it overflows.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn pull(
+        dc: &datachannel_facade::DataChannel,
+        buffer: &crate::recv_buffer::RecvBuffer,
+        state: &ChannelState,
+    ) {
+        use datachannel_facade::platform::native::DataChannelExt as _;
+
+        {
+            let _pulling = state.pulling.lock().unwrap();
+            while buffer.wants_pull() {
+                match dc.receive() {
+                    Ok(Some(message)) => buffer.push(message),
+                    Ok(None) => break,
+                    Err(e) => buffer.push_error(e.into()),
+                }
+            }
+        }
+        state.close_if_overflowed(buffer, dc);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn pull(
+        _dc: &datachannel_facade::DataChannel,
+        _buffer: &crate::recv_buffer::RecvBuffer,
+        _state: &ChannelState,
+    ) {
+    }
+
+    /// Set the maximum number of received datagrams to buffer, and what to do with datagrams that arrive when the
+    /// buffer is full.
+    ///
+    /// By default, the buffer is unbounded.
+    pub fn set_recv_buffer_capacity(&self, capacity: usize, overflow_policy: OverflowPolicy) {
+        self.buffer.set_capacity(capacity, overflow_policy);
+    }
+
+    /// The number of received datagrams that have been dropped due to the receive buffer being full.
+    pub fn dropped_count(&self) -> u64 {
+        self.buffer.dropped_count()
+    }
+
+    /// The label of the channel.
+    pub fn label(&self) -> Result<String, Error> {
+        Ok(self.dc.label()?)
+    }
+
+    /// The sub-protocol of the channel, or the empty string if there is none.
+    pub fn protocol(&self) -> Result<String, Error> {
+        Ok(self.dc.protocol()?)
+    }
+
+    /// The SCTP stream id of the channel, or [`None`] if it has not been assigned yet.
+    pub fn id(&self) -> Result<Option<u16>, Error> {
+        Ok(self.dc.id()?)
+    }
+
+    /// Whether messages on the channel are delivered in order.
+    pub fn ordered(&self) -> Result<bool, Error> {
+        Ok(self.dc.ordered()?)
+    }
+
+    /// The maximum number of milliseconds that attempts to transfer a message may take, if the channel is unreliable.
+    pub fn max_packet_life_time(&self) -> Result<Option<u16>, Error> {
+        Ok(self.dc.max_packet_life_time()?)
+    }
+
+    /// The maximum number of times a message may be retransmitted, if the channel is unreliable.
+    pub fn max_retransmits(&self) -> Result<Option<u16>, Error> {
+        Ok(self.dc.max_retransmits()?)
+    }
+
+    /// The state of the channel's underlying data connection.
+    pub fn ready_state(&self) -> Result<DataChannelState, Error> {
+        Ok(self.dc.ready_state()?)
     }
 
     /// Rejoin the Receiver with its Sender.
@@ -24,22 +225,347 @@ impl Receiver {
     }
 }
 
+impl futures::Stream for Receiver {
+    type Item = Result<Message, Error>;
+
+    fn poll_next(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
 /// The sender half of a channel.
+///
+/// Datagrams can also be sent through the [`futures::Sink`] implementation, which waits for the send buffer to drain in
+/// the same way as [`Sender::send`]. Closing the sink closes the channel.
+///
+/// Senders can be cloned to send from multiple tasks. Clones share the underlying channel, which stays open until every
+/// sender and the receiver have been dropped, or it is closed explicitly. Messages sent from each clone are delivered in
+/// the order that clone sent them.
 pub struct Sender {
-    is_open_notify: std::sync::Arc<crate::sync_util::PermanentNotify>,
-    dc: datachannel_facade::DataChannel,
+    state: std::sync::Arc<ChannelState>,
+    sink_listener: Option<event_listener::EventListener>,
+    sink_flushing: Option<Flushing>,
+    sink_closing: bool,
+    dc: std::sync::Arc<datachannel_facade::DataChannel>,
+}
+
+/// Lowers the buffered amount low threshold to 0 while a flush is in progress, so that it is woken up when the buffer
+/// drains completely. The usual threshold is restored once no flushes are in progress.
+pub(crate) struct Flushing {
+    state: std::sync::Arc<ChannelState>,
+    dc: std::sync::Arc<datachannel_facade::DataChannel>,
+}
+
+impl Flushing {
+    fn new(sender: &Sender) -> Result<Self, Error> {
+        sender
+            .state
+            .flushing
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let flushing = Self {
+            state: std::sync::Arc::clone(&sender.state),
+            dc: std::sync::Arc::clone(&sender.dc),
+        };
+        update_buffered_amount_low_threshold(&flushing.state, &flushing.dc)?;
+        Ok(flushing)
+    }
+}
+
+impl Drop for Flushing {
+    fn drop(&mut self) {
+        self.state
+            .flushing
+            .fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+        if let Err(e) = update_buffered_amount_low_threshold(&self.state, &self.dc) {
+            log::warn!("failed to restore buffered amount low threshold: {e}");
+        }
+    }
+}
+
+/// Set the buffered amount low threshold to 0 if a flush is in progress, or to half of the high threshold otherwise.
+fn update_buffered_amount_low_threshold(
+    state: &ChannelState,
+    dc: &datachannel_facade::DataChannel,
+) -> Result<(), Error> {
+    let value = if state.flushing.load(std::sync::atomic::Ordering::SeqCst) > 0 {
+        0
+    } else {
+        state
+            .buffered_amount_high_threshold
+            .load(std::sync::atomic::Ordering::SeqCst)
+            / 2
+    };
+    dc.set_buffered_amount_low_threshold(value)?;
+    Ok(())
 }
 
 impl Sender {
-    /// Send a datagram to the channel.
-    pub async fn send(&self, buf: &[u8]) -> Result<(), std::io::Error> {
-        self.is_open_notify.notified().await;
-        self.dc
-            .send(buf)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    /// Send a message to the channel.
+    ///
+    /// If more than the high threshold of bytes are already buffered for sending, this will wait until the buffer has
+    /// drained before sending. If the channel closes or errors while waiting, this returns [`Error::Closed`], or
+    /// [`Error::ConnectionFailed`] if the peer connection failed.
+    pub async fn send(&self, message: Message) -> Result<(), Error> {
+        match &message {
+            Message::Text(s) => self.send_text(s).await,
+            Message::Binary(buf) => self.send_binary(buf).await,
+        }
+    }
+
+    /// Send a message to the channel without waiting.
+    ///
+    /// Unlike [`Sender::send`], this returns [`Error::NotOpen`] if the channel is not open yet, and
+    /// [`Error::BufferFull`] if more than the high threshold of bytes are already buffered for sending.
+    pub fn try_send(&self, message: Message) -> Result<(), Error> {
+        if self.state.is_done() {
+            return Err(self.state.closed_error());
+        }
+        if !self.state.is_open_notify.is_notified() {
+            return Err(Error::NotOpen);
+        }
+        if self.dc.buffered_amount()? > self.buffered_amount_high_threshold() {
+            return Err(Error::BufferFull);
+        }
+        match &message {
+            Message::Text(s) => self.send_text_now(s),
+            Message::Binary(buf) => self.send_binary_now(buf),
+        }
+    }
+
+    /// Send a binary datagram to the channel.
+    ///
+    /// If more than the high threshold of bytes are already buffered for sending, this will wait until the buffer has
+    /// drained before sending.
+    pub async fn send_binary(&self, buf: &[u8]) -> Result<(), Error> {
+        self.ready_to_send().await?;
+        self.send_binary_now(buf)
+    }
+
+    /// Send a text datagram to the channel.
+    ///
+    /// If more than the high threshold of bytes are already buffered for sending, this will wait until the buffer has
+    /// drained before sending.
+    pub async fn send_text(&self, s: &str) -> Result<(), Error> {
+        self.ready_to_send().await?;
+        self.send_text_now(s)
+    }
+
+    /// Send a binary datagram without waiting for the channel to be ready.
+    pub(crate) fn send_binary_now(&self, buf: &[u8]) -> Result<(), Error> {
+        self.check_message_size(buf.len())?;
+        self.dc.send(buf)?;
+        Ok(())
+    }
+
+    /// Send a text datagram without waiting for the channel to be ready.
+    pub(crate) fn send_text_now(&self, s: &str) -> Result<(), Error> {
+        self.check_message_size(s.len())?;
+        self.dc.send_text(s)?;
+        Ok(())
+    }
+
+    fn check_message_size(&self, size: usize) -> Result<(), Error> {
+        let max_size = self.max_message_size()?;
+        if size > max_size {
+            return Err(Error::MessageTooLarge { size, max_size });
+        }
         Ok(())
     }
 
+    /// The maximum size of a message that can be sent on the channel.
+    pub fn max_message_size(&self) -> Result<usize, Error> {
+        Ok(self.dc.max_message_size()?)
+    }
+
+    /// Close the channel once all pending sends have been flushed to the remote peer.
+    ///
+    /// This waits until the channel has finished closing.
+    pub async fn close(&self) -> Result<(), Error> {
+        let mut listener = None;
+        let mut flushing = None;
+        let mut closing = false;
+        std::future::poll_fn(|cx| {
+            self.poll_shutdown(cx, &mut listener, &mut flushing, &mut closing)
+        })
+        .await
+    }
+
+    /// Wait for the channel to close, returning why it was closed.
+    pub async fn closed(&self) -> CloseReason {
+        self.state.closed().await
+    }
+
+    /// Poll until pending sends are flushed, then close the channel and poll until it has finished closing.
+    ///
+    /// `flushing` holds the flush in progress and `closing` records whether the channel has been closed between polls.
+    pub(crate) fn poll_shutdown(
+        &self,
+        cx: &mut std::task::Context<'_>,
+        listener: &mut Option<event_listener::EventListener>,
+        flushing: &mut Option<Flushing>,
+        closing: &mut bool,
+    ) -> std::task::Poll<Result<(), Error>> {
+        if !*closing {
+            futures::ready!(self.poll_flushed(cx, listener, flushing))?;
+            self.state
+                .closing_locally
+                .store(true, std::sync::atomic::Ordering::SeqCst);
+            self.dc.close()?;
+            *closing = true;
+        }
+        self.poll_closed(cx, listener).map(Ok)
+    }
+
+    /// Poll until no bytes are buffered for sending, or the channel has closed.
+    ///
+    /// `flushing` holds the flush in progress between polls, and the usual buffered amount low threshold is restored
+    /// once it is done or dropped.
+    fn poll_flushed(
+        &self,
+        cx: &mut std::task::Context<'_>,
+        listener: &mut Option<event_listener::EventListener>,
+        flushing: &mut Option<Flushing>,
+    ) -> std::task::Poll<Result<(), Error>> {
+        // Be woken up when the buffer drains completely, rather than when it crosses the usual low threshold.
+        if flushing.is_none() {
+            *flushing = Some(Flushing::new(self)?);
+        }
+        loop {
+            let l = listener.get_or_insert_with(|| self.state.writable_event.listen());
+            if self.state.is_done() {
+                *listener = None;
+                *flushing = None;
+                return std::task::Poll::Ready(Ok(()));
+            }
+            let buffered_amount = match self.dc.buffered_amount() {
+                Ok(buffered_amount) => buffered_amount,
+                Err(e) => {
+                    *listener = None;
+                    *flushing = None;
+                    return std::task::Poll::Ready(Err(e.into()));
+                }
+            };
+            if buffered_amount == 0 {
+                *listener = None;
+                *flushing = None;
+                return std::task::Poll::Ready(Ok(()));
+            }
+            futures::ready!(std::future::Future::poll(std::pin::Pin::new(l), cx));
+            *listener = None;
+        }
+    }
+
+    async fn ready_to_send(&self) -> Result<(), Error> {
+        let mut listener = None;
+        std::future::poll_fn(|cx| self.poll_writable(cx, &mut listener)).await
+    }
+
+    /// Poll until the channel is open and no more than the high threshold of bytes are buffered for sending.
+    ///
+    /// `listener` holds the registration for the next wakeup between polls.
+    pub(crate) fn poll_writable(
+        &self,
+        cx: &mut std::task::Context<'_>,
+        listener: &mut Option<event_listener::EventListener>,
+    ) -> std::task::Poll<Result<(), Error>> {
+        loop {
+            let l = listener.get_or_insert_with(|| self.state.writable_event.listen());
+            if self.state.is_done() {
+                *listener = None;
+                return std::task::Poll::Ready(Err(self.state.closed_error()));
+            }
+            if self.state.is_open_notify.is_notified() {
+                let buffered_amount = match self.dc.buffered_amount() {
+                    Ok(buffered_amount) => buffered_amount,
+                    Err(e) => {
+                        *listener = None;
+                        return std::task::Poll::Ready(Err(e.into()));
+                    }
+                };
+                if buffered_amount <= self.buffered_amount_high_threshold() {
+                    *listener = None;
+                    return std::task::Poll::Ready(Ok(()));
+                }
+            }
+            futures::ready!(std::future::Future::poll(std::pin::Pin::new(l), cx));
+            *listener = None;
+        }
+    }
+
+    /// Poll until the channel has finished closing.
+    pub(crate) fn poll_closed(
+        &self,
+        cx: &mut std::task::Context<'_>,
+        listener: &mut Option<event_listener::EventListener>,
+    ) -> std::task::Poll<()> {
+        loop {
+            let l = listener.get_or_insert_with(|| self.state.writable_event.listen());
+            if self.state.is_closed_notify.is_notified() {
+                *listener = None;
+                return std::task::Poll::Ready(());
+            }
+            futures::ready!(std::future::Future::poll(std::pin::Pin::new(l), cx));
+            *listener = None;
+        }
+    }
+
+    /// The number of buffered outgoing bytes above which [`Sender::send`] will wait for the buffer to drain.
+    pub fn buffered_amount_high_threshold(&self) -> u32 {
+        self.state
+            .buffered_amount_high_threshold
+            .load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    /// Set the number of buffered outgoing bytes above which [`Sender::send`] will wait for the buffer to drain.
+    ///
+    /// Waiting senders are resumed once the buffer has drained to half of this value.
+    pub fn set_buffered_amount_high_threshold(&self, value: u32) -> Result<(), Error> {
+        self.state
+            .buffered_amount_high_threshold
+            .store(value, std::sync::atomic::Ordering::SeqCst);
+        update_buffered_amount_low_threshold(&self.state, &self.dc)?;
+        self.state.writable_event.notify(usize::MAX);
+        Ok(())
+    }
+
+    /// The label of the channel.
+    pub fn label(&self) -> Result<String, Error> {
+        Ok(self.dc.label()?)
+    }
+
+    /// The sub-protocol of the channel, or the empty string if there is none.
+    pub fn protocol(&self) -> Result<String, Error> {
+        Ok(self.dc.protocol()?)
+    }
+
+    /// The SCTP stream id of the channel, or [`None`] if it has not been assigned yet.
+    pub fn id(&self) -> Result<Option<u16>, Error> {
+        Ok(self.dc.id()?)
+    }
+
+    /// Whether messages on the channel are delivered in order.
+    pub fn ordered(&self) -> Result<bool, Error> {
+        Ok(self.dc.ordered()?)
+    }
+
+    /// The maximum number of milliseconds that attempts to transfer a message may take, if the channel is unreliable.
+    pub fn max_packet_life_time(&self) -> Result<Option<u16>, Error> {
+        Ok(self.dc.max_packet_life_time()?)
+    }
+
+    /// The maximum number of times a message may be retransmitted, if the channel is unreliable.
+    pub fn max_retransmits(&self) -> Result<Option<u16>, Error> {
+        Ok(self.dc.max_retransmits()?)
+    }
+
+    /// The state of the channel's underlying data connection.
+    pub fn ready_state(&self) -> Result<DataChannelState, Error> {
+        Ok(self.dc.ready_state()?)
+    }
+
     /// Rejoin the Sender with its Receiver.
     pub fn unsplit(self, receiver: Receiver) -> Channel {
         Channel {
@@ -49,6 +575,63 @@ impl Sender {
     }
 }
 
+impl Clone for Sender {
+    fn clone(&self) -> Self {
+        Self {
+            state: std::sync::Arc::clone(&self.state),
+            sink_listener: None,
+            sink_flushing: None,
+            sink_closing: false,
+            dc: std::sync::Arc::clone(&self.dc),
+        }
+    }
+}
+
+impl futures::Sink<Message> for Sender {
+    type Error = Error;
+
+    fn poll_ready(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let mut listener = this.sink_listener.take();
+        let result = this.poll_writable(cx, &mut listener);
+        this.sink_listener = listener;
+        result
+    }
+
+    fn start_send(self: std::pin::Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        match &item {
+            Message::Text(s) => self.send_text_now(s),
+            Message::Binary(buf) => self.send_binary_now(buf),
+        }
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        // Messages are handed to the channel as soon as they are sent, so there is nothing to flush.
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let mut listener = this.sink_listener.take();
+        let mut flushing = this.sink_flushing.take();
+        let mut closing = this.sink_closing;
+        let result = this.poll_shutdown(cx, &mut listener, &mut flushing, &mut closing);
+        this.sink_listener = listener;
+        this.sink_flushing = flushing;
+        this.sink_closing = closing;
+        result
+    }
+}
+
 /// A Channel is a WebRTC DataChannel that datagrams can be sent and received on.
 pub struct Channel {
     receiver: Receiver,
@@ -56,54 +639,271 @@ pub struct Channel {
 }
 
 impl Channel {
-    pub(crate) fn wrap(mut dc: datachannel_facade::DataChannel, is_open: bool) -> Channel {
-        let is_open_notify = std::sync::Arc::new(crate::sync_util::PermanentNotify::new());
+    pub(crate) fn wrap(
+        mut dc: datachannel_facade::DataChannel,
+        is_open: bool,
+        connection_failed_notify: std::sync::Arc<crate::sync_util::PermanentNotify>,
+    ) -> Channel {
+        let state = std::sync::Arc::new(ChannelState {
+            is_open_notify: crate::sync_util::PermanentNotify::new(),
+            is_closed_notify: crate::sync_util::PermanentNotify::new(),
+            connection_failed_notify,
+            writable_event: event_listener::Event::new(),
+            buffered_amount_high_threshold: DEFAULT_BUFFERED_AMOUNT_HIGH_THRESHOLD.into(),
+            closing_locally: false.into(),
+            flushing: 0.into(),
+            overflowed: false.into(),
+            pulling: std::sync::Mutex::new(()),
+            error: std::sync::Mutex::new(None),
+            close_reason: std::sync::Mutex::new(None),
+        });
         if is_open {
-            is_open_notify.notify();
+            state.is_open_notify.notify();
         }
 
-        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let buffer = std::sync::Arc::new(crate::recv_buffer::RecvBuffer::new());
+
+        // Incoming messages are handled as they arrive, so that an overflowing channel is closed even if nobody is
+        // receiving from it. That needs the channel itself, which is only shared once its callbacks are set.
+        let weak_dc = std::sync::Arc::new(std::sync::OnceLock::<
+            std::sync::Weak<datachannel_facade::DataChannel>,
+        >::new());
 
         dc.set_on_open(Some({
-            let is_open_notify = std::sync::Arc::clone(&is_open_notify);
+            let state = std::sync::Arc::clone(&state);
             move || {
-                is_open_notify.notify();
+                state.is_open_notify.notify();
+                state.writable_event.notify(usize::MAX);
             }
         }));
-        dc.set_on_message(Some({
-            let tx = tx.clone();
-            move |buf: &[u8]| {
-                let _ = tx.unbounded_send(Ok(buf.to_vec()));
+        cfg_if::cfg_if! {
+            if #[cfg(target_arch = "wasm32")] {
+                dc.set_on_message(Some({
+                    let buffer = std::sync::Arc::clone(&buffer);
+                    let state = std::sync::Arc::clone(&state);
+                    let weak_dc = std::sync::Arc::clone(&weak_dc);
+                    move |message: Message| {
+                        buffer.push(message);
+                        if let Some(dc) = weak_dc.get().and_then(std::sync::Weak::upgrade) {
+                            state.close_if_overflowed(&buffer, &dc);
+                        }
+                    }
+                }));
+            } else {
+                use datachannel_facade::platform::native::DataChannelExt as _;
+
+                dc.set_on_available(Some({
+                    let buffer = std::sync::Arc::clone(&buffer);
+                    let state = std::sync::Arc::clone(&state);
+                    let weak_dc = std::sync::Arc::clone(&weak_dc);
+                    move || {
+                        if let Some(dc) = weak_dc.get().and_then(std::sync::Weak::upgrade) {
+                            Receiver::pull(&dc, &buffer, &state);
+                        }
+                        buffer.notify();
+                    }
+                }));
             }
-        }));
+        }
         dc.set_on_error(Some({
-            let tx = tx.clone();
+            let buffer = std::sync::Arc::clone(&buffer);
+            let state = std::sync::Arc::clone(&state);
             move |err: datachannel_facade::Error| {
-                let _ = tx.unbounded_send(Err(err));
-                tx.close_channel();
+                state.set_error(err.to_string());
+                buffer.push_error(err.into());
             }
         }));
         dc.set_on_close(Some({
-            let tx = tx.clone();
+            let buffer = std::sync::Arc::clone(&buffer);
+            let state = std::sync::Arc::clone(&state);
+            move || {
+                state.set_closed();
+                buffer.close();
+            }
+        }));
+        dc.set_on_buffered_amount_low(Some({
+            let state = std::sync::Arc::clone(&state);
             move || {
-                tx.close_channel();
+                state.writable_event.notify(usize::MAX);
             }
         }));
 
+        if let Err(e) =
+            dc.set_buffered_amount_low_threshold(DEFAULT_BUFFERED_AMOUNT_HIGH_THRESHOLD / 2)
+        {
+            log::warn!("failed to set buffered amount low threshold: {e}");
+        }
+
+        let dc = std::sync::Arc::new(dc);
+        let _ = weak_dc.set(std::sync::Arc::downgrade(&dc));
+
         Channel {
-            receiver: Receiver { rx },
-            sender: Sender { dc, is_open_notify },
+            receiver: Receiver {
+                buffer,
+                listener: None,
+                state: std::sync::Arc::clone(&state),
+                dc: std::sync::Arc::clone(&dc),
+            },
+            sender: Sender {
+                dc,
+                state,
+                sink_listener: None,
+                sink_flushing: None,
+                sink_closing: false,
+            },
         }
     }
 
-    /// Receive a datagram from the channel, or [`None`] if the channel is closed.
-    pub async fn recv(&mut self) -> Result<Vec<u8>, std::io::Error> {
+    /// Receive a datagram from the channel.
+    ///
+    /// Once the channel is closed and all received datagrams have been consumed, this returns [`Error::Closed`], or
+    /// [`Error::ConnectionFailed`] if the channel was closed because the peer connection failed.
+    pub async fn recv(&mut self) -> Result<Message, Error> {
         self.receiver.recv().await
     }
 
-    /// Send a datagram to the channel.
-    pub async fn send(&self, buf: &[u8]) -> Result<(), std::io::Error> {
-        self.sender.send(buf).await
+    /// Send a message to the channel.
+    ///
+    /// If more than the high threshold of bytes are already buffered for sending, this will wait until the buffer has
+    /// drained before sending.
+    pub async fn send(&self, message: Message) -> Result<(), Error> {
+        self.sender.send(message).await
+    }
+
+    /// Send a message to the channel without waiting.
+    ///
+    /// Unlike [`Channel::send`], this returns [`Error::NotOpen`] if the channel is not open yet, and
+    /// [`Error::BufferFull`] if more than the high threshold of bytes are already buffered for sending.
+    pub fn try_send(&self, message: Message) -> Result<(), Error> {
+        self.sender.try_send(message)
+    }
+
+    /// Send a binary datagram to the channel.
+    ///
+    /// If more than the high threshold of bytes are already buffered for sending, this will wait until the buffer has
+    /// drained before sending.
+    pub async fn send_binary(&self, buf: &[u8]) -> Result<(), Error> {
+        self.sender.send_binary(buf).await
+    }
+
+    /// Close the channel once all pending sends have been flushed to the remote peer.
+    ///
+    /// This waits until the channel has finished closing.
+    pub async fn close(&self) -> Result<(), Error> {
+        self.sender.close().await
+    }
+
+    /// Wait for the channel to close, returning why it was closed.
+    pub async fn closed(&self) -> CloseReason {
+        self.sender.closed().await
+    }
+
+    /// Send a text datagram to the channel.
+    ///
+    /// If more than the high threshold of bytes are already buffered for sending, this will wait until the buffer has
+    /// drained before sending.
+    pub async fn send_text(&self, s: &str) -> Result<(), Error> {
+        self.sender.send_text(s).await
+    }
+
+    /// The number of buffered outgoing bytes above which [`Channel::send`] will wait for the buffer to drain.
+    pub fn buffered_amount_high_threshold(&self) -> u32 {
+        self.sender.buffered_amount_high_threshold()
+    }
+
+    /// Set the number of buffered outgoing bytes above which [`Channel::send`] will wait for the buffer to drain.
+    ///
+    /// Waiting senders are resumed once the buffer has drained to half of this value.
+    pub fn set_buffered_amount_high_threshold(&self, value: u32) -> Result<(), Error> {
+        self.sender.set_buffered_amount_high_threshold(value)
+    }
+
+    /// Set the maximum number of received datagrams to buffer, and what to do with datagrams that arrive when the
+    /// buffer is full.
+    ///
+    /// By default, the buffer is unbounded.
+    pub fn set_recv_buffer_capacity(&self, capacity: usize, overflow_policy: OverflowPolicy) {
+        self.receiver
+            .set_recv_buffer_capacity(capacity, overflow_policy)
+    }
+
+    /// The number of received datagrams that have been dropped due to the receive buffer being full.
+    pub fn dropped_count(&self) -> u64 {
+        self.receiver.dropped_count()
+    }
+
+    /// Convert the channel into a byte stream implementing [`futures::io::AsyncRead`] and [`futures::io::AsyncWrite`].
+    ///
+    /// Message boundaries are not preserved, so only reliable, ordered channels can be converted.
+    pub fn into_stream(self) -> Result<crate::ByteStream, Error> {
+        self.check_reliable_ordered()?;
+        Ok(crate::ByteStream::new(self))
+    }
+
+    /// Convert the channel into one that fragments messages larger than the maximum message size.
+    ///
+    /// Received messages larger than `max_reassembled_size` are rejected. Fragments must arrive in order and without
+    /// loss, so only reliable, ordered channels can be converted.
+    pub fn into_fragmenting(
+        self,
+        max_reassembled_size: usize,
+    ) -> Result<crate::FragmentingChannel, Error> {
+        self.check_reliable_ordered()?;
+        Ok(crate::FragmentingChannel::new(self, max_reassembled_size))
+    }
+
+    fn check_reliable_ordered(&self) -> Result<(), Error> {
+        let dc = &self.sender.dc;
+        if !dc.ordered()? || dc.max_packet_life_time()?.is_some() || dc.max_retransmits()?.is_some()
+        {
+            return Err(Error::NotReliableOrdered);
+        }
+        Ok(())
+    }
+
+    /// The label of the channel.
+    pub fn label(&self) -> Result<String, Error> {
+        self.sender.label()
+    }
+
+    /// The sub-protocol of the channel, or the empty string if there is none.
+    pub fn protocol(&self) -> Result<String, Error> {
+        self.sender.protocol()
+    }
+
+    /// The SCTP stream id of the channel, or [`None`] if it has not been assigned yet.
+    pub fn id(&self) -> Result<Option<u16>, Error> {
+        self.sender.id()
+    }
+
+    /// Whether messages on the channel are delivered in order.
+    pub fn ordered(&self) -> Result<bool, Error> {
+        self.sender.ordered()
+    }
+
+    /// The maximum number of milliseconds that attempts to transfer a message may take, if the channel is unreliable.
+    pub fn max_packet_life_time(&self) -> Result<Option<u16>, Error> {
+        self.sender.max_packet_life_time()
+    }
+
+    /// The maximum number of times a message may be retransmitted, if the channel is unreliable.
+    pub fn max_retransmits(&self) -> Result<Option<u16>, Error> {
+        self.sender.max_retransmits()
+    }
+
+    /// The state of the channel's underlying data connection.
+    pub fn ready_state(&self) -> Result<DataChannelState, Error> {
+        self.sender.ready_state()
+    }
+
+    /// The maximum size of a message that can be sent on the channel.
+    pub fn max_message_size(&self) -> Result<usize, Error> {
+        self.sender.max_message_size()
+    }
+
+    /// A [`Sender`] for the channel, sharing it with this channel.
+    pub fn sender(&self) -> Sender {
+        self.sender.clone()
     }
 
     /// Split the channel into [`Sender`] and [`Receiver`] halves.
@@ -111,3 +911,209 @@ impl Channel {
         (self.sender, self.receiver)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_channel_send_backpressure() {
+        let (_conn1, chan1, _conn2, mut chan2) = crate::test::negotiated_channel_pair().await;
+
+        chan1.set_buffered_amount_high_threshold(1).unwrap();
+
+        let buf = vec![0xab; 1024];
+        for _ in 0..64 {
+            chan1.send_binary(&buf).await.unwrap();
+        }
+        for _ in 0..64 {
+            assert_eq!(chan2.recv().await.unwrap(), Message::Binary(buf.clone()));
+        }
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_channel_recv_buffer_overflow_close() {
+        let (_conn1, chan1, _conn2, mut chan2) = crate::test::negotiated_channel_pair().await;
+
+        chan2.set_recv_buffer_capacity(1, OverflowPolicy::Close);
+        for i in 0..3u8 {
+            chan1.send_binary(&[i]).await.unwrap();
+        }
+
+        // The channel is closed on overflow, without anyone receiving from it.
+        assert_eq!(chan1.closed().await, CloseReason::Remote);
+        assert_eq!(
+            chan2.closed().await,
+            CloseReason::Error(Error::RecvBufferOverflowed.to_string())
+        );
+
+        assert_eq!(chan2.recv().await.unwrap(), Message::Binary(vec![0]));
+        assert!(matches!(
+            chan2.recv().await,
+            Err(Error::RecvBufferOverflowed)
+        ));
+        assert!(matches!(chan2.recv().await, Err(Error::Closed)));
+        assert!(chan2.dropped_count() >= 1);
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_channel_stream_sink() {
+        use futures::{SinkExt as _, StreamExt as _};
+
+        let (_conn1, chan1, _conn2, chan2) = crate::test::negotiated_channel_pair().await;
+        let (mut sender, _receiver) = chan1.split();
+        let (_sender, receiver) = chan2.split();
+
+        let messages = vec![
+            Message::Binary(b"hello".to_vec()),
+            Message::Text("world".to_string()),
+        ];
+        sender
+            .send_all(&mut futures::stream::iter(messages.clone()).map(Ok))
+            .await
+            .unwrap();
+        futures::SinkExt::close(&mut sender).await.unwrap();
+
+        let received = receiver
+            .map(|message| message.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(received, messages);
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_channel_try_send_not_open() {
+        let cb = crate::Connection::builder(Default::default()).unwrap();
+        let chan = cb.create_data_channel("test", Default::default()).unwrap();
+        let _conn = cb.build();
+
+        let err = chan
+            .try_send(Message::Binary(b"hello".to_vec()))
+            .unwrap_err();
+        assert!(matches!(err, Error::NotOpen));
+        assert!(err.is_retryable());
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_channel_close() {
+        let (_conn1, chan1, _conn2, mut chan2) = crate::test::negotiated_channel_pair().await;
+
+        let buf = vec![0xab; 1024];
+        for _ in 0..64 {
+            chan1.send_binary(&buf).await.unwrap();
+        }
+        chan1.close().await.unwrap();
+        assert_eq!(chan1.closed().await, CloseReason::Local);
+
+        for _ in 0..64 {
+            assert_eq!(chan2.recv().await.unwrap(), Message::Binary(buf.clone()));
+        }
+        assert!(matches!(chan2.recv().await, Err(Error::Closed)));
+        assert_eq!(chan2.closed().await, CloseReason::Remote);
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_channel_flush_dropped() {
+        let (_conn1, chan1, _conn2, mut chan2) = crate::test::negotiated_channel_pair().await;
+
+        chan1.set_buffered_amount_high_threshold(1).unwrap();
+
+        let buf = vec![0xab; 1024];
+        for _ in 0..64 {
+            chan1.send_binary(&buf).await.unwrap();
+        }
+
+        // A flush that is dropped before it completes restores the usual low threshold, so senders are still resumed.
+        {
+            let mut listener = None;
+            let mut flushing = None;
+            let _ = chan1.sender.poll_flushed(
+                &mut std::task::Context::from_waker(futures::task::noop_waker_ref()),
+                &mut listener,
+                &mut flushing,
+            );
+        }
+        assert_eq!(
+            chan1
+                .sender
+                .state
+                .flushing
+                .load(std::sync::atomic::Ordering::SeqCst),
+            0
+        );
+
+        for _ in 0..64 {
+            chan1.send_binary(&buf).await.unwrap();
+        }
+        for _ in 0..128 {
+            assert_eq!(chan2.recv().await.unwrap(), Message::Binary(buf.clone()));
+        }
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_channel_metadata() {
+        let (_conn1, chan1, _conn2, mut chan2) = crate::test::negotiated_channel_pair().await;
+
+        chan1.send_binary(b"hello").await.unwrap();
+        chan2.recv().await.unwrap();
+
+        for chan in [&chan1, &chan2] {
+            assert_eq!(chan.label().unwrap(), "test");
+            assert_eq!(chan.protocol().unwrap(), "");
+            assert_eq!(chan.id().unwrap(), Some(1));
+            assert!(chan.ordered().unwrap());
+            assert_eq!(chan.max_packet_life_time().unwrap(), None);
+            assert_eq!(chan.max_retransmits().unwrap(), None);
+            assert_eq!(chan.ready_state().unwrap(), DataChannelState::Open);
+        }
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_channel_sender_clone() {
+        let (_conn1, chan1, _conn2, mut chan2) = crate::test::negotiated_channel_pair().await;
+
+        let (sender1, receiver1) = chan1.split();
+        let sender2 = sender1.clone();
+        futures::join!(
+            async {
+                for i in 0..16u8 {
+                    sender1.send_binary(&[0, i]).await.unwrap();
+                }
+            },
+            async {
+                for i in 0..16u8 {
+                    sender2.send_binary(&[1, i]).await.unwrap();
+                }
+            },
+        );
+
+        let mut next = [0u8; 2];
+        for _ in 0..32 {
+            let Message::Binary(buf) = chan2.recv().await.unwrap() else {
+                panic!("expected binary message");
+            };
+            let producer = buf[0] as usize;
+            assert_eq!(buf[1], next[producer]);
+            next[producer] += 1;
+        }
+
+        drop(sender1);
+        sender2.send_binary(b"still open").await.unwrap();
+        assert_eq!(
+            chan2.recv().await.unwrap(),
+            Message::Binary(b"still open".to_vec())
+        );
+
+        drop(sender2);
+        drop(receiver1);
+        assert!(matches!(chan2.recv().await, Err(Error::Closed)));
+    }
+}
@@ -1,12 +1,43 @@
 pub use datachannel_facade::Configuration;
 pub use datachannel_facade::DataChannelOptions;
 pub use datachannel_facade::Description;
-pub use datachannel_facade::Error;
 pub use datachannel_facade::IceGatheringState;
 pub use datachannel_facade::PeerConnectionState;
 pub use datachannel_facade::SdpType;
+pub use datachannel_facade::SignalingState;
+use futures::FutureExt as _;
 use futures::StreamExt as _;
 
+use crate::Error;
+
+/// The most state changes that are set aside by the per-event methods, such as [`Connection::accept_channel`], for
+/// later.
+const MAX_PENDING_STATE_CHANGES: usize = 64;
+
+/// An event from a [`Connection`].
+pub enum ConnectionEvent {
+    /// A local ICE candidate was gathered, which should be sent to the remote peer. [`None`] indicates that all
+    /// candidates have been gathered.
+    IceCandidate(Option<String>),
+
+    /// The ICE gathering state changed.
+    IceGatheringStateChange(IceGatheringState),
+
+    /// The connection state changed.
+    ConnectionStateChange(PeerConnectionState),
+
+    /// The remote peer opened a channel.
+    DataChannel(crate::Channel),
+
+    /// The signaling state changed.
+    SignalingStateChange(SignalingState),
+
+    /// The connection needs to be renegotiated with the remote peer.
+    ///
+    /// On native, this is only sent once, when the first channel is created.
+    NegotiationNeeded,
+}
+
 pub struct ConnectionBuilder(Connection);
 
 impl ConnectionBuilder {
@@ -18,6 +49,7 @@ impl ConnectionBuilder {
         Ok(crate::Channel::wrap(
             self.0.pc.create_data_channel(label, options)?,
             false,
+            std::sync::Arc::clone(&self.0.connection_failed_notify),
         ))
     }
 
@@ -27,54 +59,93 @@ impl ConnectionBuilder {
 }
 
 pub struct Connection {
-    pc: datachannel_facade::PeerConnection,
-    ice_candidates_rx: futures::channel::mpsc::UnboundedReceiver<String>,
+    pc: std::sync::Arc<datachannel_facade::PeerConnection>,
+    events_rx: futures::channel::mpsc::UnboundedReceiver<ConnectionEvent>,
+    pending_events: std::collections::VecDeque<ConnectionEvent>,
+    ice_candidates_done: bool,
     ice_candidates_gathered_notify: std::sync::Arc<crate::sync_util::PermanentNotify>,
-    peer_connection_states_rx: futures::channel::mpsc::UnboundedReceiver<PeerConnectionState>,
-    data_channels_rx: futures::channel::mpsc::UnboundedReceiver<datachannel_facade::DataChannel>,
+    connection_failed_notify: std::sync::Arc<crate::sync_util::PermanentNotify>,
+    connection_state: std::sync::Arc<crate::watch::Watched<PeerConnectionState>>,
+    ice_gathering_state: std::sync::Arc<crate::watch::Watched<IceGatheringState>>,
 }
 
 impl Connection {
     fn wrap(mut pc: datachannel_facade::PeerConnection) -> Self {
         let ice_candidates_gathered_notify =
             std::sync::Arc::new(crate::sync_util::PermanentNotify::new());
+        let connection_failed_notify =
+            std::sync::Arc::new(crate::sync_util::PermanentNotify::new());
+        let connection_state =
+            std::sync::Arc::new(crate::watch::Watched::new(PeerConnectionState::New));
+        let ice_gathering_state =
+            std::sync::Arc::new(crate::watch::Watched::new(IceGatheringState::New));
+
+        let (events_tx, events_rx) = futures::channel::mpsc::unbounded();
 
-        let (ice_candidates_tx, ice_candidates_rx) = futures::channel::mpsc::unbounded();
-        let (peer_connection_states_tx, peer_connection_states_rx) =
-            futures::channel::mpsc::unbounded();
-        let (data_channels_tx, data_channels_rx) = futures::channel::mpsc::unbounded();
-
-        pc.set_on_ice_candidate(Some(move |cand: Option<&str>| {
-            let cand = if let Some(cand) = cand {
-                cand
-            } else {
-                ice_candidates_tx.close_channel();
-                return;
-            };
-            let _ = ice_candidates_tx.unbounded_send(cand.to_string());
+        pc.set_on_ice_candidate(Some({
+            let events_tx = events_tx.clone();
+            move |cand: Option<&str>| {
+                let _ = events_tx.unbounded_send(ConnectionEvent::IceCandidate(
+                    cand.map(|cand| cand.to_string()),
+                ));
+            }
         }));
         pc.set_on_ice_gathering_state_change(Some({
             let ice_candidates_gathered_notify =
                 std::sync::Arc::clone(&ice_candidates_gathered_notify);
+            let ice_gathering_state = std::sync::Arc::clone(&ice_gathering_state);
+            let events_tx = events_tx.clone();
             move |state: IceGatheringState| {
+                ice_gathering_state.set(state);
                 if state == IceGatheringState::Complete {
                     ice_candidates_gathered_notify.notify();
                 }
+                let _ = events_tx.unbounded_send(ConnectionEvent::IceGatheringStateChange(state));
             }
         }));
-        pc.set_on_connection_state_change(Some(move |state: PeerConnectionState| {
-            let _ = peer_connection_states_tx.unbounded_send(state);
+        pc.set_on_connection_state_change(Some({
+            let connection_failed_notify = std::sync::Arc::clone(&connection_failed_notify);
+            let connection_state = std::sync::Arc::clone(&connection_state);
+            let events_tx = events_tx.clone();
+            move |state: PeerConnectionState| {
+                if state == PeerConnectionState::Failed {
+                    connection_failed_notify.notify();
+                }
+                connection_state.set(state);
+                let _ = events_tx.unbounded_send(ConnectionEvent::ConnectionStateChange(state));
+            }
         }));
-        pc.set_on_data_channel(Some(move |dc: datachannel_facade::DataChannel| {
-            let _ = data_channels_tx.unbounded_send(dc);
+        pc.set_on_data_channel(Some({
+            let connection_failed_notify = std::sync::Arc::clone(&connection_failed_notify);
+            let events_tx = events_tx.clone();
+            move |dc: datachannel_facade::DataChannel| {
+                let _ =
+                    events_tx.unbounded_send(ConnectionEvent::DataChannel(crate::Channel::wrap(
+                        dc,
+                        true,
+                        std::sync::Arc::clone(&connection_failed_notify),
+                    )));
+            }
+        }));
+        pc.set_on_signaling_state_change(Some({
+            let events_tx = events_tx.clone();
+            move |state: SignalingState| {
+                let _ = events_tx.unbounded_send(ConnectionEvent::SignalingStateChange(state));
+            }
+        }));
+        pc.set_on_negotiation_needed(Some(move || {
+            let _ = events_tx.unbounded_send(ConnectionEvent::NegotiationNeeded);
         }));
 
         Self {
-            pc,
-            ice_candidates_rx,
+            pc: std::sync::Arc::new(pc),
+            events_rx,
+            pending_events: std::collections::VecDeque::new(),
+            ice_candidates_done: false,
             ice_candidates_gathered_notify,
-            peer_connection_states_rx,
-            data_channels_rx,
+            connection_failed_notify,
+            connection_state,
+            ice_gathering_state,
         }
     }
 
@@ -84,8 +155,66 @@ impl Connection {
         )))
     }
 
+    /// Receive the next event from the connection.
+    pub async fn next_event(&mut self) -> Option<ConnectionEvent> {
+        std::future::poll_fn(|cx| self.poll_next_event(cx)).await
+    }
+
+    /// Receive all events from the connection as a stream.
+    ///
+    /// Events that have already been passed over by the per-event methods, such as [`Connection::accept_channel`], are
+    /// yielded first. Channels and candidates are always kept, but only a limited number of state changes are, as the
+    /// latest states can still be observed through [`Connection::watch_state`] and
+    /// [`Connection::watch_ice_gathering_state`].
+    pub fn events(&mut self) -> impl futures::Stream<Item = ConnectionEvent> + Unpin + '_ {
+        futures::stream::poll_fn(|cx| self.poll_next_event(cx))
+    }
+
+    fn poll_next_event(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<ConnectionEvent>> {
+        let event = match self.pending_events.pop_front() {
+            Some(event) => Some(event),
+            None => futures::ready!(self.events_rx.poll_next_unpin(cx)),
+        };
+        if let Some(ConnectionEvent::IceCandidate(None)) = event {
+            self.ice_candidates_done = true;
+        }
+        std::task::Poll::Ready(event)
+    }
+
+    /// Receive the next event matching the predicate, setting aside other events for later.
+    async fn next_matching_event(
+        &mut self,
+        mut f: impl FnMut(&ConnectionEvent) -> bool,
+    ) -> Option<ConnectionEvent> {
+        if let Some(i) = self.pending_events.iter().position(&mut f) {
+            return self.pending_events.remove(i);
+        }
+        loop {
+            let event = self.events_rx.next().await?;
+            if f(&event) {
+                return Some(event);
+            }
+            set_aside(&mut self.pending_events, event);
+        }
+    }
+
     pub async fn next_ice_candidate(&mut self) -> Option<String> {
-        self.ice_candidates_rx.next().await
+        if self.ice_candidates_done {
+            return None;
+        }
+        match self
+            .next_matching_event(|event| matches!(event, ConnectionEvent::IceCandidate(_)))
+            .await?
+        {
+            ConnectionEvent::IceCandidate(Some(cand)) => Some(cand),
+            _ => {
+                self.ice_candidates_done = true;
+                None
+            }
+        }
     }
 
     pub async fn ice_candidates_gathered(&self) {
@@ -93,37 +222,396 @@ impl Connection {
     }
 
     pub async fn next_connection_state(&mut self) -> Option<PeerConnectionState> {
-        self.peer_connection_states_rx.next().await
+        match self
+            .next_matching_event(|event| matches!(event, ConnectionEvent::ConnectionStateChange(_)))
+            .await?
+        {
+            ConnectionEvent::ConnectionStateChange(state) => Some(state),
+            _ => unreachable!(),
+        }
+    }
+
+    /// The current state of the connection.
+    pub fn state(&self) -> PeerConnectionState {
+        self.connection_state.get()
+    }
+
+    /// Watch the state of the connection.
+    ///
+    /// Unlike [`Connection::next_connection_state`], any number of watchers can observe the state at once.
+    pub fn watch_state(&self) -> crate::Watcher<PeerConnectionState> {
+        crate::Watcher::new(std::sync::Arc::clone(&self.connection_state))
+    }
+
+    /// Wait for the connection to be connected.
+    ///
+    /// This fails early with [`Error::ConnectionFailed`] or [`Error::ConnectionClosed`] if the connection fails or is
+    /// closed first, and with [`Error::Timeout`] if it is not connected within the timeout.
+    pub async fn wait_connected(&self, timeout: std::time::Duration) -> Result<(), Error> {
+        let mut watcher = self.watch_state();
+        let state = crate::sync_util::timeout(
+            timeout,
+            watcher.wait_for(|state| {
+                matches!(
+                    state,
+                    PeerConnectionState::Connected
+                        | PeerConnectionState::Failed
+                        | PeerConnectionState::Closed
+                )
+            }),
+        )
+        .await
+        .ok_or(Error::Timeout)?;
+        match state {
+            PeerConnectionState::Failed => Err(Error::ConnectionFailed),
+            PeerConnectionState::Closed => Err(Error::ConnectionClosed),
+            _ => Ok(()),
+        }
+    }
+
+    /// The current ICE gathering state of the connection.
+    pub fn ice_gathering_state(&self) -> IceGatheringState {
+        self.ice_gathering_state.get()
+    }
+
+    /// Watch the ICE gathering state of the connection.
+    pub fn watch_ice_gathering_state(&self) -> crate::Watcher<IceGatheringState> {
+        crate::Watcher::new(std::sync::Arc::clone(&self.ice_gathering_state))
+    }
+
+    /// Wait for ICE candidate gathering to complete.
+    ///
+    /// This fails early with [`Error::ConnectionFailed`] or [`Error::ConnectionClosed`] if the connection fails or is
+    /// closed first, and with [`Error::Timeout`] if gathering does not complete within the timeout.
+    pub async fn wait_ice_candidates_gathered(
+        &self,
+        timeout: std::time::Duration,
+    ) -> Result<(), Error> {
+        let mut watcher = self.watch_state();
+        crate::sync_util::timeout(timeout, async {
+            futures::select_biased! {
+                () = self.ice_candidates_gathered().fuse() => Ok(()),
+                state = watcher
+                    .wait_for(|state| {
+                        matches!(state, PeerConnectionState::Failed | PeerConnectionState::Closed)
+                    })
+                    .fuse() => {
+                    if state == PeerConnectionState::Failed {
+                        Err(Error::ConnectionFailed)
+                    } else {
+                        Err(Error::ConnectionClosed)
+                    }
+                }
+            }
+        })
+        .await
+        .ok_or(Error::Timeout)?
     }
 
     pub async fn accept_channel(&mut self) -> Option<crate::Channel> {
-        Some(super::Channel::wrap(
-            self.data_channels_rx.next().await?,
-            true,
-        ))
+        match self
+            .next_matching_event(|event| matches!(event, ConnectionEvent::DataChannel(_)))
+            .await?
+        {
+            ConnectionEvent::DataChannel(channel) => Some(channel),
+            _ => unreachable!(),
+        }
     }
 
     pub fn close(&self) -> Result<(), Error> {
-        self.pc.close()
+        close(&self.pc, &self.connection_state)
+    }
+
+    /// A handle that can close the connection from elsewhere, without owning it.
+    pub fn handle(&self) -> ConnectionHandle {
+        ConnectionHandle {
+            pc: std::sync::Arc::downgrade(&self.pc),
+            connection_state: std::sync::Arc::clone(&self.connection_state),
+        }
     }
 
     pub async fn set_local_description(&self, type_: SdpType) -> Result<(), Error> {
-        self.pc.set_local_description(type_).await
+        Ok(self.pc.set_local_description(type_).await?)
     }
 
     pub async fn set_remote_description(&self, description: &Description) -> Result<(), Error> {
-        self.pc.set_remote_description(description).await
+        Ok(self.pc.set_remote_description(description).await?)
     }
 
     pub fn local_description(&self) -> Result<Option<Description>, Error> {
-        self.pc.local_description()
+        Ok(self.pc.local_description()?)
     }
 
     pub fn remote_description(&self) -> Result<Option<Description>, Error> {
-        self.pc.remote_description()
+        Ok(self.pc.remote_description()?)
+    }
+
+    pub async fn add_ice_candidate(
+        &self,
+        cand: Option<&str>,
+        mid: Option<&str>,
+    ) -> Result<(), Error> {
+        Ok(self.pc.add_ice_candidate(cand, mid).await?)
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // Dropping the connection closes it, so let watchers know.
+        if !matches!(
+            self.connection_state.get(),
+            PeerConnectionState::Failed | PeerConnectionState::Closed
+        ) {
+            self.connection_state.set(PeerConnectionState::Closed);
+        }
+    }
+}
+
+fn is_state_change(event: &ConnectionEvent) -> bool {
+    matches!(
+        event,
+        ConnectionEvent::IceGatheringStateChange(_)
+            | ConnectionEvent::ConnectionStateChange(_)
+            | ConnectionEvent::SignalingStateChange(_)
+    )
+}
+
+/// Keep an event for later.
+///
+/// Channels and candidates are always kept. State changes make room for each other by dropping the oldest one, and a
+/// pending negotiation needed event stands for any later ones.
+fn set_aside(
+    pending_events: &mut std::collections::VecDeque<ConnectionEvent>,
+    event: ConnectionEvent,
+) {
+    if matches!(event, ConnectionEvent::NegotiationNeeded)
+        && pending_events
+            .iter()
+            .any(|event| matches!(event, ConnectionEvent::NegotiationNeeded))
+    {
+        return;
+    }
+    if is_state_change(&event)
+        && pending_events
+            .iter()
+            .filter(|event| is_state_change(event))
+            .count()
+            >= MAX_PENDING_STATE_CHANGES
+    {
+        let i = pending_events.iter().position(is_state_change).unwrap();
+        pending_events.remove(i);
+        log::debug!("dropping unhandled connection state change");
+    }
+    pending_events.push_back(event);
+}
+
+fn close(
+    pc: &datachannel_facade::PeerConnection,
+    connection_state: &crate::watch::Watched<PeerConnectionState>,
+) -> Result<(), Error> {
+    pc.close()?;
+    // Closing locally does not reliably fire a connection state change, so record it here.
+    connection_state.set(PeerConnectionState::Closed);
+    Ok(())
+}
+
+/// A cloneable handle to a [`Connection`], for observing and closing it from elsewhere.
+///
+/// The handle does not keep the connection alive: once the connection is dropped, closing it through the handle does
+/// nothing.
+#[derive(Clone)]
+pub struct ConnectionHandle {
+    pc: std::sync::Weak<datachannel_facade::PeerConnection>,
+    connection_state: std::sync::Arc<crate::watch::Watched<PeerConnectionState>>,
+}
+
+impl ConnectionHandle {
+    /// Close the connection, if it still exists.
+    pub fn close(&self) -> Result<(), Error> {
+        let Some(pc) = self.pc.upgrade() else {
+            return Ok(());
+        };
+        close(&pc, &self.connection_state)
+    }
+
+    /// The current state of the connection.
+    pub fn state(&self) -> PeerConnectionState {
+        self.connection_state.get()
+    }
+
+    /// Watch the state of the connection.
+    pub fn watch_state(&self) -> crate::Watcher<PeerConnectionState> {
+        crate::Watcher::new(std::sync::Arc::clone(&self.connection_state))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_set_aside() {
+        let mut pending_events = std::collections::VecDeque::new();
+        for i in 0..MAX_PENDING_STATE_CHANGES {
+            set_aside(
+                &mut pending_events,
+                ConnectionEvent::IceCandidate(Some(i.to_string())),
+            );
+            set_aside(
+                &mut pending_events,
+                ConnectionEvent::ConnectionStateChange(PeerConnectionState::Connecting),
+            );
+        }
+        set_aside(&mut pending_events, ConnectionEvent::NegotiationNeeded);
+        set_aside(&mut pending_events, ConnectionEvent::NegotiationNeeded);
+        assert_eq!(pending_events.len(), 2 * MAX_PENDING_STATE_CHANGES + 1);
+
+        // Only the oldest state change makes room for a new one.
+        set_aside(
+            &mut pending_events,
+            ConnectionEvent::ConnectionStateChange(PeerConnectionState::Connected),
+        );
+        assert_eq!(pending_events.len(), 2 * MAX_PENDING_STATE_CHANGES + 1);
+        assert!(matches!(
+            pending_events.front(),
+            Some(ConnectionEvent::IceCandidate(Some(cand))) if cand == "0"
+        ));
+        assert!(matches!(
+            pending_events.back(),
+            Some(ConnectionEvent::ConnectionStateChange(
+                PeerConnectionState::Connected
+            ))
+        ));
+
+        // Candidates are never dropped.
+        for i in MAX_PENDING_STATE_CHANGES..2 * MAX_PENDING_STATE_CHANGES {
+            set_aside(
+                &mut pending_events,
+                ConnectionEvent::IceCandidate(Some(i.to_string())),
+            );
+        }
+        let candidates = pending_events
+            .iter()
+            .filter_map(|event| match event {
+                ConnectionEvent::IceCandidate(Some(cand)) => Some(cand.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            candidates,
+            (0..2 * MAX_PENDING_STATE_CHANGES)
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_set_aside_channels() {
+        let cb = Connection::builder(Default::default()).unwrap();
+        let mut pending_events = std::collections::VecDeque::new();
+        for i in 0..MAX_PENDING_STATE_CHANGES + 1 {
+            let channel = cb
+                .create_data_channel(&i.to_string(), Default::default())
+                .unwrap();
+            set_aside(&mut pending_events, ConnectionEvent::DataChannel(channel));
+        }
+        set_aside(
+            &mut pending_events,
+            ConnectionEvent::IceGatheringStateChange(IceGatheringState::Gathering),
+        );
+        assert_eq!(pending_events.len(), MAX_PENDING_STATE_CHANGES + 2);
+        assert!(matches!(
+            pending_events.front(),
+            Some(ConnectionEvent::DataChannel(channel)) if channel.label().unwrap() == "0"
+        ));
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_connection_wait_connected() {
+        let (conn1, _chan1, conn2, _chan2) = crate::test::negotiated_channel_pair().await;
+
+        assert_eq!(conn1.ice_gathering_state(), IceGatheringState::Complete);
+        conn1
+            .wait_ice_candidates_gathered(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+
+        let mut watcher = conn2.watch_state();
+        conn1
+            .wait_connected(std::time::Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert_eq!(conn1.state(), PeerConnectionState::Connected);
+        assert_eq!(
+            watcher
+                .wait_for(|state| *state == PeerConnectionState::Connected)
+                .await,
+            PeerConnectionState::Connected
+        );
+
+        conn1.close().unwrap();
+        assert!(matches!(
+            conn1
+                .wait_connected(std::time::Duration::from_secs(10))
+                .await,
+            Err(Error::ConnectionClosed)
+        ));
     }
 
-    pub async fn add_ice_candidate(&self, cand: Option<&str>) -> Result<(), Error> {
-        self.pc.add_ice_candidate(cand).await
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_connection_events() {
+        let cb1 = Connection::builder(Default::default()).unwrap();
+        let _chan1 = cb1.create_data_channel("test", Default::default()).unwrap();
+        let mut conn1 = cb1.build();
+
+        let mut negotiation_needed = false;
+        while let Some(event) = conn1.next_event().await {
+            if let ConnectionEvent::NegotiationNeeded = event {
+                negotiation_needed = true;
+                break;
+            }
+        }
+        assert!(negotiation_needed);
+
+        conn1.set_local_description(SdpType::Offer).await.unwrap();
+        let mut signaling_states = vec![];
+        let mut candidates = 0;
+        while let Some(event) = conn1.next_event().await {
+            match event {
+                ConnectionEvent::SignalingStateChange(state) => signaling_states.push(state),
+                ConnectionEvent::IceCandidate(Some(_)) => candidates += 1,
+                ConnectionEvent::IceGatheringStateChange(IceGatheringState::Complete) => break,
+                _ => {}
+            }
+        }
+        assert!(signaling_states.contains(&SignalingState::HaveLocalOffer));
+        assert!(candidates > 0);
+
+        let cb2 = Connection::builder(Default::default()).unwrap();
+        let mut conn2 = cb2.build();
+        conn2
+            .set_remote_description(&conn1.local_description().unwrap().unwrap())
+            .await
+            .unwrap();
+        conn2.set_local_description(SdpType::Answer).await.unwrap();
+        conn2.ice_candidates_gathered().await;
+        conn1
+            .set_remote_description(&conn2.local_description().unwrap().unwrap())
+            .await
+            .unwrap();
+
+        let mut events = conn2.events();
+        let chan2 = loop {
+            if let ConnectionEvent::DataChannel(channel) =
+                futures::StreamExt::next(&mut events).await.unwrap()
+            {
+                break channel;
+            }
+        };
+        assert_eq!(chan2.label().unwrap(), "test");
     }
 }
@@ -0,0 +1,76 @@
+/// An error from a connection or channel.
+#[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The channel was closed, either by the remote peer or locally.
+    #[error("channel closed")]
+    Closed,
+
+    /// The peer connection failed, taking its channels with it.
+    #[error("connection failed")]
+    ConnectionFailed,
+
+    /// The peer connection was closed.
+    #[error("connection closed")]
+    ConnectionClosed,
+
+    /// The operation did not complete in time.
+    #[error("timed out")]
+    Timeout,
+
+    /// The message is larger than the maximum message size of the channel.
+    #[error("message of {size} bytes exceeds maximum message size of {max_size} bytes")]
+    MessageTooLarge { size: usize, max_size: usize },
+
+    /// The channel is not open yet.
+    #[error("channel not open yet")]
+    NotOpen,
+
+    /// More than the high threshold of bytes are buffered for sending.
+    #[error("send buffer full")]
+    BufferFull,
+
+    /// The receive buffer overflowed with [`crate::OverflowPolicy::Close`], and the channel was closed.
+    #[error("receive buffer overflowed")]
+    RecvBufferOverflowed,
+
+    /// The channel is unordered or unreliable, but the operation requires a reliable, ordered channel.
+    #[error("channel is not reliable and ordered")]
+    NotReliableOrdered,
+
+    /// A message received from the remote peer could not be understood.
+    #[error("malformed message: {0}")]
+    MalformedMessage(String),
+
+    /// An error from the underlying WebRTC implementation.
+    #[error("platform: {0}")]
+    Platform(#[from] datachannel_facade::Error),
+}
+
+impl Error {
+    /// Whether the operation may succeed if it is retried later.
+    ///
+    /// All other errors are fatal, either for the operation or for the channel itself.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Error::NotOpen | Error::BufferFull)
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(value: Error) -> Self {
+        let kind = match &value {
+            Error::Closed => std::io::ErrorKind::BrokenPipe,
+            Error::ConnectionFailed => std::io::ErrorKind::ConnectionAborted,
+            Error::ConnectionClosed => std::io::ErrorKind::NotConnected,
+            Error::Timeout => std::io::ErrorKind::TimedOut,
+            Error::MessageTooLarge { .. } | Error::NotReliableOrdered => {
+                std::io::ErrorKind::InvalidInput
+            }
+            Error::NotOpen => std::io::ErrorKind::NotConnected,
+            Error::BufferFull => std::io::ErrorKind::WouldBlock,
+            Error::MalformedMessage(_) => std::io::ErrorKind::InvalidData,
+            Error::RecvBufferOverflowed | Error::Platform(_) => std::io::ErrorKind::Other,
+        };
+        std::io::Error::new(kind, value)
+    }
+}
@@ -0,0 +1,251 @@
+/// The default maximum size of a message reassembled by a [`FragmentingChannel`].
+pub const DEFAULT_MAX_REASSEMBLED_SIZE: usize = 16 * 1024 * 1024;
+
+/// Set on the last fragment of a message.
+const FLAG_FINAL: u8 = 0b01;
+
+/// Set on every fragment of a text message.
+const FLAG_TEXT: u8 = 0b10;
+
+/// A channel that splits messages larger than the maximum message size into fragments and reassembles them on receipt.
+///
+/// Every fragment carries a one byte header, so both peers must wrap their end of the channel in a
+/// [`FragmentingChannel`].
+pub struct FragmentingChannel {
+    channel: crate::Channel,
+    reassembler: Reassembler,
+}
+
+impl FragmentingChannel {
+    pub(crate) fn new(channel: crate::Channel, max_reassembled_size: usize) -> Self {
+        Self {
+            channel,
+            reassembler: Reassembler::new(max_reassembled_size),
+        }
+    }
+
+    /// Receive a message from the channel, reassembling it from its fragments.
+    ///
+    /// If the reassembled message would be larger than the maximum reassembled size, the rest of its fragments are
+    /// discarded and [`crate::Error::MessageTooLarge`] is returned.
+    pub async fn recv(&mut self) -> Result<crate::Message, crate::Error> {
+        loop {
+            let crate::Message::Binary(fragment) = self.channel.recv().await? else {
+                return Err(crate::Error::MalformedMessage(
+                    "fragment is not binary".to_string(),
+                ));
+            };
+            if let Some(message) = self.reassembler.push(&fragment)? {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Send a message to the channel, splitting it into fragments no larger than the maximum message size.
+    ///
+    /// This takes `&mut self` so that the fragments of concurrent sends cannot be interleaved.
+    pub async fn send(&mut self, message: &crate::Message) -> Result<(), crate::Error> {
+        let fragment_size = self.channel.max_message_size()?.max(2) - 1;
+        for fragment in fragment(message, fragment_size) {
+            self.channel.send_binary(&fragment).await?;
+        }
+        Ok(())
+    }
+
+    /// The maximum size of a reassembled message.
+    pub fn max_reassembled_size(&self) -> usize {
+        self.reassembler.max_size
+    }
+
+    /// Unwrap the underlying channel.
+    pub fn into_inner(self) -> crate::Channel {
+        self.channel
+    }
+}
+
+fn fragment(message: &crate::Message, fragment_size: usize) -> impl Iterator<Item = Vec<u8>> + '_ {
+    let text_flag = if matches!(message, crate::Message::Text(_)) {
+        FLAG_TEXT
+    } else {
+        0
+    };
+    let buf = message.as_bytes();
+    let n = std::cmp::max(buf.len().div_ceil(fragment_size), 1);
+    (0..n).map(move |i| {
+        let chunk = &buf[std::cmp::min(i * fragment_size, buf.len())
+            ..std::cmp::min((i + 1) * fragment_size, buf.len())];
+        let mut fragment = Vec::with_capacity(chunk.len() + 1);
+        fragment.push(text_flag | if i == n - 1 { FLAG_FINAL } else { 0 });
+        fragment.extend_from_slice(chunk);
+        fragment
+    })
+}
+
+struct Reassembler {
+    max_size: usize,
+    buf: Vec<u8>,
+    is_text: Option<bool>,
+    discarding: bool,
+}
+
+impl Reassembler {
+    fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            buf: vec![],
+            is_text: None,
+            discarding: false,
+        }
+    }
+
+    /// Add a fragment, returning the reassembled message if it was the last one.
+    fn push(&mut self, fragment: &[u8]) -> Result<Option<crate::Message>, crate::Error> {
+        let Some((&header, payload)) = fragment.split_first() else {
+            return Err(crate::Error::MalformedMessage("empty fragment".to_string()));
+        };
+        if header & !(FLAG_FINAL | FLAG_TEXT) != 0 {
+            return Err(crate::Error::MalformedMessage(
+                "unknown fragment flags".to_string(),
+            ));
+        }
+        let is_final = header & FLAG_FINAL != 0;
+
+        if self.discarding {
+            self.discarding = !is_final;
+            return Ok(None);
+        }
+
+        let size = self.buf.len() + payload.len();
+        if size > self.max_size {
+            self.buf = vec![];
+            self.is_text = None;
+            self.discarding = !is_final;
+            return Err(crate::Error::MessageTooLarge {
+                size,
+                max_size: self.max_size,
+            });
+        }
+
+        let is_text = *self.is_text.get_or_insert(header & FLAG_TEXT != 0);
+        self.buf.extend_from_slice(payload);
+        if !is_final {
+            return Ok(None);
+        }
+
+        let buf = std::mem::take(&mut self.buf);
+        self.is_text = None;
+        Ok(Some(if is_text {
+            crate::Message::Text(
+                String::from_utf8(buf)
+                    .map_err(|e| crate::Error::MalformedMessage(e.to_string()))?,
+            )
+        } else {
+            crate::Message::Binary(buf)
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Message;
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_fragment_roundtrip() {
+        let mut reassembler = Reassembler::new(DEFAULT_MAX_REASSEMBLED_SIZE);
+        for message in [
+            Message::Binary(vec![]),
+            Message::Binary((0..100).collect()),
+            Message::Text("hello world!".to_string()),
+        ] {
+            let fragments = fragment(&message, 7).collect::<Vec<_>>();
+            let (last, rest) = fragments.split_last().unwrap();
+            for f in rest {
+                assert!(f.len() <= 8);
+                assert_eq!(reassembler.push(f).unwrap(), None);
+            }
+            assert_eq!(reassembler.push(last).unwrap(), Some(message));
+        }
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_fragment_max_reassembled_size() {
+        let mut reassembler = Reassembler::new(10);
+        let fragments = fragment(&Message::Binary(vec![0; 20]), 4).collect::<Vec<_>>();
+        assert_eq!(reassembler.push(&fragments[0]).unwrap(), None);
+        assert_eq!(reassembler.push(&fragments[1]).unwrap(), None);
+        assert!(reassembler.push(&fragments[2]).is_err());
+        assert_eq!(reassembler.push(&fragments[3]).unwrap(), None);
+        assert_eq!(reassembler.push(&fragments[4]).unwrap(), None);
+
+        let message = Message::Binary(vec![1; 10]);
+        for f in fragment(&message, 4) {
+            if let Some(m) = reassembler.push(&f).unwrap() {
+                assert_eq!(m, message);
+                return;
+            }
+        }
+        panic!("message was not reassembled");
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_fragment_malformed() {
+        let mut reassembler = Reassembler::new(DEFAULT_MAX_REASSEMBLED_SIZE);
+        assert!(matches!(
+            reassembler.push(&[]),
+            Err(crate::Error::MalformedMessage(_))
+        ));
+        assert!(matches!(
+            reassembler.push(&[0b100, 0]),
+            Err(crate::Error::MalformedMessage(_))
+        ));
+        assert!(matches!(
+            reassembler.push(&[FLAG_TEXT | FLAG_FINAL, 0xff]),
+            Err(crate::Error::MalformedMessage(_))
+        ));
+
+        // The reassembler recovers for the next message.
+        let message = Message::Text("hello".to_string());
+        let fragments = fragment(&message, 2).collect::<Vec<_>>();
+        let (last, rest) = fragments.split_last().unwrap();
+        for f in rest {
+            assert_eq!(reassembler.push(f).unwrap(), None);
+        }
+        assert_eq!(reassembler.push(last).unwrap(), Some(message));
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_fragmenting_channel() {
+        let (_conn1, chan1, _conn2, chan2) = crate::test::negotiated_channel_pair().await;
+
+        // A fragment that is not binary is rejected.
+        chan1.send_text("not a fragment").await.unwrap();
+        let mut chan1 = chan1
+            .into_fragmenting(DEFAULT_MAX_REASSEMBLED_SIZE)
+            .unwrap();
+        let mut chan2 = chan2
+            .into_fragmenting(DEFAULT_MAX_REASSEMBLED_SIZE)
+            .unwrap();
+        assert!(matches!(
+            chan2.recv().await,
+            Err(crate::Error::MalformedMessage(_))
+        ));
+
+        // Messages larger than the maximum message size are fragmented.
+        let message = Message::Binary((0..1024 * 1024).map(|i| i as u8).collect());
+        chan1.send(&message).await.unwrap();
+        chan1
+            .send(&Message::Text("small".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(chan2.recv().await.unwrap(), message);
+        assert_eq!(
+            chan2.recv().await.unwrap(),
+            Message::Text("small".to_string())
+        );
+    }
+}
@@ -1,12 +1,26 @@
+mod recv_buffer;
 mod sync_util;
 
+mod byte_stream;
 mod channel;
 mod connection;
-
+mod error;
+mod fragment;
+mod router;
+#[cfg(feature = "serde")]
+mod typed;
+mod watch;
+
+pub use byte_stream::*;
 pub use channel::*;
 pub use connection::*;
+pub use error::*;
+pub use fragment::*;
+pub use router::*;
+#[cfg(feature = "serde")]
+pub use typed::*;
+pub use watch::*;
 
-pub use datachannel_facade::Error;
 pub use datachannel_facade::IceServer;
 pub use datachannel_facade::IceTransportPolicy;
 
@@ -72,11 +86,23 @@ mod test {
             .await
             .unwrap();
 
-        chan1.send(b"hello world!").await.unwrap();
-        assert_eq!(chan2.recv().await.unwrap(), b"hello world!");
-
-        chan2.send(b"goodbye world!").await.unwrap();
-        assert_eq!(chan1.recv().await.unwrap(), b"goodbye world!");
+        chan1.send_binary(b"hello world!").await.unwrap();
+        assert_eq!(
+            chan2.recv().await.unwrap(),
+            Message::Binary(b"hello world!".to_vec())
+        );
+
+        chan2.send_binary(b"goodbye world!").await.unwrap();
+        assert_eq!(
+            chan1.recv().await.unwrap(),
+            Message::Binary(b"goodbye world!".to_vec())
+        );
+
+        chan1.send_text("hello text!").await.unwrap();
+        assert_eq!(
+            chan2.recv().await.unwrap(),
+            Message::Text("hello text!".to_string())
+        );
     }
 
     #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
@@ -105,10 +131,48 @@ mod test {
 
         let mut chan2 = conn2.accept_channel().await.unwrap();
 
-        chan1.send(b"hello world!").await.unwrap();
-        assert_eq!(chan2.recv().await.unwrap(), b"hello world!");
+        chan1.send_binary(b"hello world!").await.unwrap();
+        assert_eq!(
+            chan2.recv().await.unwrap(),
+            Message::Binary(b"hello world!".to_vec())
+        );
+
+        chan2.send_binary(b"goodbye world!").await.unwrap();
+        assert_eq!(
+            chan1.recv().await.unwrap(),
+            Message::Binary(b"goodbye world!".to_vec())
+        );
+    }
+
+    /// Connect two connections with a negotiated channel between them.
+    pub(crate) async fn negotiated_channel_pair() -> (Connection, Channel, Connection, Channel) {
+        let options = || DataChannelOptions {
+            negotiated: true,
+            id: Some(1),
+            ..Default::default()
+        };
+
+        let cb1 = Connection::builder(Default::default()).unwrap();
+        let chan1 = cb1.create_data_channel("test", options()).unwrap();
+        let conn1 = cb1.build();
+        conn1.set_local_description(SdpType::Offer).await.unwrap();
+        conn1.ice_candidates_gathered().await;
+
+        let cb2 = Connection::builder(Default::default()).unwrap();
+        let chan2 = cb2.create_data_channel("test", options()).unwrap();
+        let conn2 = cb2.build();
+        conn2
+            .set_remote_description(&conn1.local_description().unwrap().unwrap())
+            .await
+            .unwrap();
+        conn2.set_local_description(SdpType::Answer).await.unwrap();
+        conn2.ice_candidates_gathered().await;
+
+        conn1
+            .set_remote_description(&conn2.local_description().unwrap().unwrap())
+            .await
+            .unwrap();
 
-        chan2.send(b"goodbye world!").await.unwrap();
-        assert_eq!(chan1.recv().await.unwrap(), b"goodbye world!");
+        (conn1, chan1, conn2, chan2)
     }
 }
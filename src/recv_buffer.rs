@@ -0,0 +1,342 @@
+/// What to do with an incoming message when the receive buffer of a channel is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Stop accepting messages from the remote peer until there is room in the buffer.
+    ///
+    /// On native, messages are left in libdatachannel's receive queue until they are received, which slows down the
+    /// remote peer via the SCTP receive window. On platforms where incoming messages cannot be held back, messages are
+    /// buffered past the capacity instead.
+    #[default]
+    Block,
+
+    /// Drop the incoming message.
+    DropNewest,
+
+    /// Drop the oldest message in the buffer to make room for the incoming message.
+    DropOldest,
+
+    /// Drop the incoming message and close the channel with an error.
+    Close,
+}
+
+struct State {
+    messages: std::collections::VecDeque<datachannel_facade::Message>,
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+    error: Option<crate::Error>,
+    closed: bool,
+    terminated: bool,
+    overflowed: bool,
+}
+
+pub struct RecvBuffer {
+    state: std::sync::Mutex<State>,
+    event: event_listener::Event,
+    dropped_count: std::sync::atomic::AtomicU64,
+}
+
+impl RecvBuffer {
+    pub fn new() -> Self {
+        Self {
+            state: std::sync::Mutex::new(State {
+                messages: std::collections::VecDeque::new(),
+                capacity: usize::MAX,
+                overflow_policy: OverflowPolicy::Block,
+                error: None,
+                closed: false,
+                terminated: false,
+                overflowed: false,
+            }),
+            event: event_listener::Event::new(),
+            dropped_count: 0.into(),
+        }
+    }
+
+    pub fn set_capacity(&self, capacity: usize, overflow_policy: OverflowPolicy) {
+        let mut state = self.state.lock().unwrap();
+        state.capacity = capacity;
+        state.overflow_policy = overflow_policy;
+    }
+
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped_count.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.state.lock().unwrap().overflowed
+    }
+
+    /// Whether more messages should be pulled into the buffer from the underlying channel.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub fn wants_pull(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        if state.terminated {
+            return false;
+        }
+        state.overflow_policy != OverflowPolicy::Block || state.messages.len() < state.capacity
+    }
+
+    pub fn push(&self, message: datachannel_facade::Message) {
+        let mut state = self.state.lock().unwrap();
+        if state.terminated {
+            return;
+        }
+
+        if state.messages.len() >= state.capacity {
+            match state.overflow_policy {
+                OverflowPolicy::Block => {}
+                OverflowPolicy::DropNewest => {
+                    self.record_dropped();
+                    return;
+                }
+                OverflowPolicy::DropOldest => {
+                    state.messages.pop_front();
+                    self.record_dropped();
+                }
+                OverflowPolicy::Close => {
+                    self.record_dropped();
+                    state.error = Some(crate::Error::RecvBufferOverflowed);
+                    state.terminated = true;
+                    state.overflowed = true;
+                    self.event.notify(usize::MAX);
+                    return;
+                }
+            }
+        }
+
+        state.messages.push_back(message);
+        self.event.notify(usize::MAX);
+    }
+
+    pub fn push_error(&self, error: crate::Error) {
+        let mut state = self.state.lock().unwrap();
+        if state.terminated {
+            return;
+        }
+        state.error = Some(error);
+        state.terminated = true;
+        self.event.notify(usize::MAX);
+    }
+
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.event.notify(usize::MAX);
+    }
+
+    /// Wake up anyone waiting in [`RecvBuffer::pop`], e.g. because messages are available to pull.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    pub fn notify(&self) {
+        self.event.notify(usize::MAX);
+    }
+
+    /// Wait for the next message, or [`None`] if the buffer is closed and drained.
+    ///
+    /// `fill` is called before checking the buffer, so messages can be pulled into it on demand.
+    #[cfg(test)]
+    pub async fn pop(
+        &self,
+        fill: impl Fn(&Self),
+    ) -> Option<Result<datachannel_facade::Message, crate::Error>> {
+        let mut listener = None;
+        std::future::poll_fn(|cx| self.poll_pop(cx, &mut listener, &fill)).await
+    }
+
+    /// Poll for the next message, or [`None`] if the buffer is closed and drained.
+    ///
+    /// `listener` holds the registration for the next wakeup between polls.
+    pub fn poll_pop(
+        &self,
+        cx: &mut std::task::Context<'_>,
+        listener: &mut Option<event_listener::EventListener>,
+        fill: impl Fn(&Self),
+    ) -> std::task::Poll<Option<Result<datachannel_facade::Message, crate::Error>>> {
+        loop {
+            let l = listener.get_or_insert_with(|| self.event.listen());
+            fill(self);
+            {
+                let mut state = self.state.lock().unwrap();
+                let result = if let Some(message) = state.messages.pop_front() {
+                    Some(Some(Ok(message)))
+                } else if let Some(error) = state.error.take() {
+                    Some(Some(Err(error)))
+                } else if state.closed || state.terminated {
+                    Some(None)
+                } else {
+                    None
+                };
+                if let Some(result) = result {
+                    *listener = None;
+                    return std::task::Poll::Ready(result);
+                }
+            }
+            futures::ready!(std::future::Future::poll(std::pin::Pin::new(l), cx));
+            *listener = None;
+        }
+    }
+
+    fn record_dropped(&self) {
+        self.dropped_count
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use datachannel_facade::Message;
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_recv_buffer_drop_oldest() {
+        let buffer = RecvBuffer::new();
+        buffer.set_capacity(2, OverflowPolicy::DropOldest);
+        buffer.push(Message::Binary(b"a".to_vec()));
+        buffer.push(Message::Binary(b"b".to_vec()));
+        buffer.push(Message::Binary(b"c".to_vec()));
+        buffer.close();
+
+        assert_eq!(
+            buffer.pop(|_| {}).await.unwrap().unwrap(),
+            Message::Binary(b"b".to_vec())
+        );
+        assert_eq!(
+            buffer.pop(|_| {}).await.unwrap().unwrap(),
+            Message::Binary(b"c".to_vec())
+        );
+        assert!(buffer.pop(|_| {}).await.is_none());
+        assert_eq!(buffer.dropped_count(), 1);
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_recv_buffer_close() {
+        let buffer = RecvBuffer::new();
+        buffer.set_capacity(1, OverflowPolicy::Close);
+        buffer.push(Message::Binary(b"a".to_vec()));
+        buffer.push(Message::Binary(b"b".to_vec()));
+        buffer.push(Message::Binary(b"c".to_vec()));
+
+        assert_eq!(
+            buffer.pop(|_| {}).await.unwrap().unwrap(),
+            Message::Binary(b"a".to_vec())
+        );
+        assert!(buffer.pop(|_| {}).await.unwrap().is_err());
+        assert!(buffer.pop(|_| {}).await.is_none());
+        assert!(buffer.overflowed());
+        assert_eq!(buffer.dropped_count(), 1);
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_recv_buffer_pull_block() {
+        let source = std::sync::Mutex::new(std::collections::VecDeque::from([
+            Message::Binary(b"a".to_vec()),
+            Message::Binary(b"b".to_vec()),
+            Message::Binary(b"c".to_vec()),
+        ]));
+        let fill = |buffer: &RecvBuffer| {
+            while buffer.wants_pull() {
+                let Some(message) = source.lock().unwrap().pop_front() else {
+                    break;
+                };
+                buffer.push(message);
+            }
+        };
+
+        let buffer = RecvBuffer::new();
+        buffer.set_capacity(2, OverflowPolicy::Block);
+        assert_eq!(
+            buffer.pop(fill).await.unwrap().unwrap(),
+            Message::Binary(b"a".to_vec())
+        );
+        assert_eq!(source.lock().unwrap().len(), 1);
+
+        buffer.set_capacity(1, OverflowPolicy::DropOldest);
+        assert_eq!(
+            buffer.pop(fill).await.unwrap().unwrap(),
+            Message::Binary(b"c".to_vec())
+        );
+        assert_eq!(buffer.dropped_count(), 1);
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_recv_buffer_drop_newest() {
+        let buffer = RecvBuffer::new();
+        buffer.set_capacity(2, OverflowPolicy::DropNewest);
+        buffer.push(Message::Binary(b"a".to_vec()));
+        buffer.push(Message::Binary(b"b".to_vec()));
+        buffer.push(Message::Binary(b"c".to_vec()));
+        buffer.close();
+
+        assert_eq!(
+            buffer.pop(|_| {}).await.unwrap().unwrap(),
+            Message::Binary(b"a".to_vec())
+        );
+        assert_eq!(
+            buffer.pop(|_| {}).await.unwrap().unwrap(),
+            Message::Binary(b"b".to_vec())
+        );
+        assert!(buffer.pop(|_| {}).await.is_none());
+        assert_eq!(buffer.dropped_count(), 1);
+        assert!(!buffer.overflowed());
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_recv_buffer_block() {
+        let buffer = RecvBuffer::new();
+        buffer.set_capacity(1, OverflowPolicy::Block);
+        assert!(buffer.wants_pull());
+
+        // Pushed messages are buffered past the capacity, but no more are pulled until there is room again.
+        buffer.push(Message::Binary(b"a".to_vec()));
+        buffer.push(Message::Binary(b"b".to_vec()));
+        assert!(!buffer.wants_pull());
+        assert_eq!(
+            buffer.pop(|_| {}).await.unwrap().unwrap(),
+            Message::Binary(b"a".to_vec())
+        );
+        assert!(!buffer.wants_pull());
+        assert_eq!(
+            buffer.pop(|_| {}).await.unwrap().unwrap(),
+            Message::Binary(b"b".to_vec())
+        );
+        assert!(buffer.wants_pull());
+        assert_eq!(buffer.dropped_count(), 0);
+
+        // Messages are pulled until the buffer is full.
+        let buffer = RecvBuffer::new();
+        buffer.set_capacity(3, OverflowPolicy::Block);
+        for message in [b"a", b"b"] {
+            buffer.push(Message::Binary(message.to_vec()));
+            assert!(buffer.wants_pull());
+        }
+        buffer.push(Message::Binary(b"c".to_vec()));
+        assert!(!buffer.wants_pull());
+        buffer.pop(|_| {}).await.unwrap().unwrap();
+        assert!(buffer.wants_pull());
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_recv_buffer_error() {
+        let buffer = RecvBuffer::new();
+        buffer.push(Message::Binary(b"a".to_vec()));
+        buffer.push_error(crate::Error::Closed);
+        buffer.push(Message::Binary(b"b".to_vec()));
+        assert!(!buffer.wants_pull());
+
+        // Buffered messages are received before the error, and nothing is accepted after it.
+        assert_eq!(
+            buffer.pop(|_| {}).await.unwrap().unwrap(),
+            Message::Binary(b"a".to_vec())
+        );
+        assert!(matches!(
+            buffer.pop(|_| {}).await,
+            Some(Err(crate::Error::Closed))
+        ));
+        assert!(buffer.pop(|_| {}).await.is_none());
+        assert!(!buffer.overflowed());
+    }
+}
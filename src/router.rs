@@ -0,0 +1,195 @@
+type Handler = Box<dyn Fn(crate::Channel) -> futures::future::BoxFuture<'static, ()> + Send + Sync>;
+
+enum Matcher {
+    Label(String),
+    Prefix {
+        prefix: String,
+        protocol: Option<String>,
+    },
+}
+
+impl Matcher {
+    fn matches(&self, label: &str, protocol: &str) -> bool {
+        match self {
+            Matcher::Label(l) => l == label,
+            Matcher::Prefix {
+                prefix,
+                protocol: p,
+            } => {
+                label.starts_with(prefix.as_str())
+                    && match p {
+                        Some(p) => p == protocol,
+                        None => true,
+                    }
+            }
+        }
+    }
+}
+
+/// Dispatches incoming channels to async handlers by label.
+///
+/// Routes are tried in the order they were added, and the first matching route handles the channel. Channels that
+/// match no route are closed, unless a fallback handler is set.
+pub struct ChannelRouter {
+    routes: Vec<(Matcher, Handler)>,
+    fallback: Option<Handler>,
+}
+
+impl ChannelRouter {
+    pub fn new() -> Self {
+        Self {
+            routes: vec![],
+            fallback: None,
+        }
+    }
+
+    /// Handle channels with exactly the given label.
+    pub fn route<F, Fut>(mut self, label: &str, handler: F) -> Self
+    where
+        F: Fn(crate::Channel) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.routes
+            .push((Matcher::Label(label.to_string()), box_handler(handler)));
+        self
+    }
+
+    /// Handle channels whose label starts with the given prefix and, if given, whose sub-protocol is the given protocol.
+    pub fn route_prefix<F, Fut>(mut self, prefix: &str, protocol: Option<&str>, handler: F) -> Self
+    where
+        F: Fn(crate::Channel) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.routes.push((
+            Matcher::Prefix {
+                prefix: prefix.to_string(),
+                protocol: protocol.map(|p| p.to_string()),
+            },
+            box_handler(handler),
+        ));
+        self
+    }
+
+    /// Handle channels that match no route, instead of closing them.
+    pub fn fallback<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(crate::Channel) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.fallback = Some(box_handler(handler));
+        self
+    }
+
+    /// Close channels that match no route. This is the default.
+    pub fn reject_unmatched(mut self) -> Self {
+        self.fallback = None;
+        self
+    }
+
+    /// Run the handler for a single channel.
+    pub fn dispatch(&self, channel: crate::Channel) -> futures::future::BoxFuture<'static, ()> {
+        let (label, protocol) = match (channel.label(), channel.protocol()) {
+            (Ok(label), Ok(protocol)) => (label, protocol),
+            (Err(e), _) | (_, Err(e)) => {
+                log::warn!("failed to get channel metadata for routing: {e}");
+                (String::new(), String::new())
+            }
+        };
+
+        let handler = self
+            .routes
+            .iter()
+            .find(|(matcher, _)| matcher.matches(&label, &protocol))
+            .map(|(_, handler)| handler)
+            .or(self.fallback.as_ref());
+
+        match handler {
+            Some(handler) => handler(channel),
+            None => Box::pin(async move {
+                log::debug!("rejecting unrouted channel {label:?} (protocol {protocol:?})");
+                if let Err(e) = channel.close().await {
+                    log::warn!("failed to close unrouted channel: {e}");
+                }
+            }),
+        }
+    }
+
+    /// Accept channels from the connection and run their handlers concurrently, until the connection is closed or
+    /// fails, or stops yielding channels, and all handlers have finished.
+    pub async fn serve(&self, conn: &mut crate::Connection) {
+        use futures::{FutureExt as _, StreamExt as _};
+
+        let mut connection_state = conn.watch_state();
+        let mut handlers = futures::stream::FuturesUnordered::new();
+        loop {
+            futures::select! {
+                channel = conn.accept_channel().fuse() => {
+                    let Some(channel) = channel else {
+                        break;
+                    };
+                    handlers.push(self.dispatch(channel));
+                },
+                () = handlers.select_next_some() => {},
+                _ = connection_state.wait_for(|state| {
+                    matches!(
+                        state,
+                        crate::PeerConnectionState::Closed | crate::PeerConnectionState::Failed
+                    )
+                }).fuse() => {
+                    break;
+                }
+            }
+        }
+        while handlers.next().await.is_some() {}
+    }
+}
+
+impl Default for ChannelRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn box_handler<F, Fut>(handler: F) -> Handler
+where
+    F: Fn(crate::Channel) -> Fut + Send + Sync + 'static,
+    Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+    Box::new(move |channel| Box::pin(handler(channel)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_matcher() {
+        assert!(Matcher::Label("chat".to_string()).matches("chat", ""));
+        assert!(!Matcher::Label("chat".to_string()).matches("chat2", ""));
+
+        let prefix = Matcher::Prefix {
+            prefix: "file/".to_string(),
+            protocol: Some("v1".to_string()),
+        };
+        assert!(prefix.matches("file/a.txt", "v1"));
+        assert!(!prefix.matches("file/a.txt", "v2"));
+        assert!(!prefix.matches("chat", "v1"));
+
+        let any_protocol = Matcher::Prefix {
+            prefix: "file/".to_string(),
+            protocol: None,
+        };
+        assert!(any_protocol.matches("file/a.txt", "v2"));
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_serve_closed_connection() {
+        let mut conn = crate::Connection::builder(Default::default())
+            .unwrap()
+            .build();
+        conn.close().unwrap();
+        ChannelRouter::new().serve(&mut conn).await;
+    }
+}
@@ -12,14 +12,33 @@ impl PermanentNotify {
     }
 
     pub async fn notified(&self) {
-        while !self.notified.load(std::sync::atomic::Ordering::SeqCst) {
-            self.event.listen().await;
+        loop {
+            let listener = self.event.listen();
+            if self.is_notified() {
+                return;
+            }
+            listener.await;
         }
     }
 
+    pub fn is_notified(&self) -> bool {
+        self.notified.load(std::sync::atomic::Ordering::SeqCst)
+    }
+
     pub fn notify(&self) {
         self.notified
             .store(true, std::sync::atomic::Ordering::SeqCst);
         self.event.notify(usize::MAX);
     }
 }
+
+/// Run a future to completion, or return [`None`] if it does not complete within the duration.
+pub async fn timeout<F: std::future::Future>(
+    duration: std::time::Duration,
+    fut: F,
+) -> Option<F::Output> {
+    match futures::future::select(std::pin::pin!(fut), futures_timer::Delay::new(duration)).await {
+        futures::future::Either::Left((output, _)) => Some(output),
+        futures::future::Either::Right(_) => None,
+    }
+}
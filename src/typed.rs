@@ -0,0 +1,237 @@
+/// A serialization format for values sent over a [`TypedChannel`].
+pub trait Codec {
+    /// Serialize a value into a message.
+    fn encode<T: serde::Serialize>(
+        &self,
+        value: &T,
+    ) -> Result<crate::Message, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// Deserialize a value from a message.
+    fn decode<T: serde::de::DeserializeOwned>(
+        &self,
+        message: &crate::Message,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// Encodes values as JSON text messages.
+#[cfg(feature = "json")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+#[cfg(feature = "json")]
+impl Codec for JsonCodec {
+    fn encode<T: serde::Serialize>(
+        &self,
+        value: &T,
+    ) -> Result<crate::Message, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(crate::Message::Text(serde_json::to_string(value)?))
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(
+        &self,
+        message: &crate::Message,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(serde_json::from_slice(message.as_bytes())?)
+    }
+}
+
+/// Encodes values as bincode binary messages.
+#[cfg(feature = "bincode")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BincodeCodec;
+
+#[cfg(feature = "bincode")]
+impl Codec for BincodeCodec {
+    fn encode<T: serde::Serialize>(
+        &self,
+        value: &T,
+    ) -> Result<crate::Message, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(crate::Message::Binary(bincode::serialize(value)?))
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(
+        &self,
+        message: &crate::Message,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(bincode::deserialize(message.as_bytes())?)
+    }
+}
+
+/// Encodes values as MessagePack binary messages, with structs encoded as maps.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePackCodec {
+    fn encode<T: serde::Serialize>(
+        &self,
+        value: &T,
+    ) -> Result<crate::Message, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(crate::Message::Binary(rmp_serde::to_vec_named(value)?))
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(
+        &self,
+        message: &crate::Message,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(rmp_serde::from_slice(message.as_bytes())?)
+    }
+}
+
+/// Encodes values as CBOR binary messages.
+#[cfg(feature = "cbor")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CborCodec;
+
+#[cfg(feature = "cbor")]
+impl Codec for CborCodec {
+    fn encode<T: serde::Serialize>(
+        &self,
+        value: &T,
+    ) -> Result<crate::Message, Box<dyn std::error::Error + Send + Sync>> {
+        let mut buf = vec![];
+        ciborium::into_writer(value, &mut buf)?;
+        Ok(crate::Message::Binary(buf))
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(
+        &self,
+        message: &crate::Message,
+    ) -> Result<T, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(ciborium::from_reader(message.as_bytes())?)
+    }
+}
+
+/// An error from a [`TypedChannel`].
+#[derive(thiserror::Error, Debug)]
+pub enum TypedError {
+    #[error("channel: {0}")]
+    Channel(#[from] crate::Error),
+
+    #[error("failed to encode message: {0}")]
+    Encode(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("failed to decode message {}: {source}", MessagePreview(.message))]
+    Decode {
+        /// The message that could not be decoded.
+        message: crate::Message,
+
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+/// Formats a message for error output, truncating long messages.
+struct MessagePreview<'a>(&'a crate::Message);
+
+impl std::fmt::Display for MessagePreview<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const MAX_LEN: usize = 64;
+
+        let buf = self.0.as_bytes();
+        let truncated = &buf[..std::cmp::min(buf.len(), MAX_LEN)];
+        match self.0 {
+            crate::Message::Text(_) => write!(f, "{:?}", String::from_utf8_lossy(truncated))?,
+            crate::Message::Binary(_) => write!(f, "{:02x?}", truncated)?,
+        }
+        if buf.len() > MAX_LEN {
+            write!(f, " (truncated from {} bytes)", buf.len())?;
+        }
+        Ok(())
+    }
+}
+
+/// A channel that sends values of type `Tx` and receives values of type `Rx`, serialized with a [`Codec`].
+pub struct TypedChannel<Tx, Rx, C> {
+    channel: crate::Channel,
+    codec: C,
+    _marker: std::marker::PhantomData<fn(Tx) -> Rx>,
+}
+
+impl<Tx, Rx, C> TypedChannel<Tx, Rx, C>
+where
+    Tx: serde::Serialize,
+    Rx: serde::de::DeserializeOwned,
+    C: Codec,
+{
+    /// Wrap a channel, encoding and decoding values with the given codec.
+    pub fn new(channel: crate::Channel, codec: C) -> Self {
+        Self {
+            channel,
+            codec,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Receive a value from the channel.
+    ///
+    /// If a message cannot be decoded, [`TypedError::Decode`] is returned with the message, and the channel can continue
+    /// to be used.
+    pub async fn recv(&mut self) -> Result<Rx, TypedError> {
+        let message = self.channel.recv().await?;
+        self.codec
+            .decode(&message)
+            .map_err(|source| TypedError::Decode { message, source })
+    }
+
+    /// Send a value to the channel.
+    pub async fn send(&self, value: &Tx) -> Result<(), TypedError> {
+        let message = self.codec.encode(value).map_err(TypedError::Encode)?;
+        self.channel.send(message).await?;
+        Ok(())
+    }
+
+    /// Unwrap the underlying channel.
+    pub fn into_inner(self) -> crate::Channel {
+        self.channel
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod test {
+    use super::*;
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_json_codec_decode_error() {
+        let message = crate::Message::Text("not json".to_string());
+        let source = JsonCodec.decode::<u32>(&message).unwrap_err();
+        let err = TypedError::Decode {
+            message: message.clone(),
+            source,
+        };
+        assert!(err.to_string().contains("\"not json\""));
+        assert!(matches!(err, TypedError::Decode { message: m, .. } if m == message));
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_message_preview() {
+        assert_eq!(
+            MessagePreview(&crate::Message::Binary(vec![0xab, 0x01])).to_string(),
+            "[ab, 01]"
+        );
+        let long = MessagePreview(&crate::Message::Text("x".repeat(100))).to_string();
+        assert!(long.starts_with(&format!("{:?}", "x".repeat(64))));
+        assert!(long.ends_with(" (truncated from 100 bytes)"));
+    }
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_typed_channel_decode_error() {
+        let (_conn1, chan1, _conn2, chan2) = crate::test::negotiated_channel_pair().await;
+
+        chan1.send_text("not json").await.unwrap();
+        let chan1 = TypedChannel::<u32, u32, _>::new(chan1, JsonCodec);
+        let mut chan2 = TypedChannel::<u32, u32, _>::new(chan2, JsonCodec);
+        chan1.send(&42).await.unwrap();
+
+        // A message that fails to decode is returned with the error, and the channel can still be used.
+        assert!(matches!(
+            chan2.recv().await,
+            Err(TypedError::Decode { message: crate::Message::Text(m), .. }) if m == "not json"
+        ));
+        assert_eq!(chan2.recv().await.unwrap(), 42);
+    }
+}
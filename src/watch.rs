@@ -0,0 +1,118 @@
+/// The current value of a watched state and the number of times it has changed.
+struct Versioned<T> {
+    value: T,
+    version: u64,
+}
+
+/// A state that can be observed by many [`Watcher`]s.
+pub(crate) struct Watched<T> {
+    current: std::sync::Mutex<Versioned<T>>,
+    event: event_listener::Event,
+}
+
+impl<T: Clone> Watched<T> {
+    pub(crate) fn new(value: T) -> Self {
+        Self {
+            current: std::sync::Mutex::new(Versioned { value, version: 0 }),
+            event: event_listener::Event::new(),
+        }
+    }
+
+    pub(crate) fn get(&self) -> T {
+        self.current.lock().unwrap().value.clone()
+    }
+
+    pub(crate) fn set(&self, value: T) {
+        let mut current = self.current.lock().unwrap();
+        current.value = value;
+        current.version += 1;
+        drop(current);
+        self.event.notify(usize::MAX);
+    }
+}
+
+/// An observer of a state, such as [`crate::Connection::watch_state`].
+///
+/// Watchers can be cloned to observe the state from multiple tasks. Each watcher only sees the latest value, so
+/// intermediate values may be skipped if the state changes faster than they are observed.
+pub struct Watcher<T> {
+    watched: std::sync::Arc<Watched<T>>,
+    seen_version: u64,
+}
+
+impl<T: Clone> Watcher<T> {
+    pub(crate) fn new(watched: std::sync::Arc<Watched<T>>) -> Self {
+        let seen_version = watched.current.lock().unwrap().version;
+        Self {
+            watched,
+            seen_version,
+        }
+    }
+
+    /// The current value, marking it as seen.
+    pub fn get(&mut self) -> T {
+        let current = self.watched.current.lock().unwrap();
+        self.seen_version = current.version;
+        current.value.clone()
+    }
+
+    /// Wait for the value to change from the last seen value, returning the new value.
+    pub async fn changed(&mut self) -> T {
+        loop {
+            let listener = self.watched.event.listen();
+            {
+                let current = self.watched.current.lock().unwrap();
+                if current.version != self.seen_version {
+                    self.seen_version = current.version;
+                    return current.value.clone();
+                }
+            }
+            listener.await;
+        }
+    }
+
+    /// Wait until the value satisfies the predicate, returning it. This returns immediately if the current value
+    /// already satisfies it.
+    pub async fn wait_for(&mut self, mut f: impl FnMut(&T) -> bool) -> T {
+        let mut value = self.get();
+        while !f(&value) {
+            value = self.changed().await;
+        }
+        value
+    }
+}
+
+impl<T> Clone for Watcher<T> {
+    fn clone(&self) -> Self {
+        Self {
+            watched: std::sync::Arc::clone(&self.watched),
+            seen_version: self.seen_version,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[cfg_attr(not(target_arch = "wasm32"), pollster::test)]
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+    pub async fn test_watcher() {
+        let watched = std::sync::Arc::new(Watched::new(0));
+        let mut watcher1 = Watcher::new(std::sync::Arc::clone(&watched));
+        let mut watcher2 = watcher1.clone();
+
+        watched.set(1);
+        watched.set(2);
+        assert_eq!(watcher1.changed().await, 2);
+        assert_eq!(watcher2.changed().await, 2);
+
+        let (value, ()) = futures::join!(watcher1.wait_for(|v| *v == 4), async {
+            watched.set(3);
+            watched.set(4);
+        });
+        assert_eq!(value, 4);
+        assert_eq!(watcher2.get(), 4);
+        assert_eq!(watcher2.wait_for(|v| *v == 4).await, 4);
+    }
+}
@@ -26,6 +26,8 @@ impl std::fmt::Display for Error {
 pub type SdpType = web_sys::RtcSdpType;
 pub type IceGatheringState = web_sys::RtcIceGatheringState;
 pub type PeerConnectionState = web_sys::RtcPeerConnectionState;
+pub type SignalingState = web_sys::RtcSignalingState;
+pub type DataChannelState = web_sys::RtcDataChannelState;
 pub type IceTransportPolicy = web_sys::RtcIceTransportPolicy;
 
 #[derive(Debug, Clone)]
@@ -131,6 +133,7 @@ impl PeerConnection {
             dc: self
                 .pc
                 .create_data_channel_with_data_channel_dict(label, &raw),
+            pc: self.pc.clone(),
         })
     }
 
@@ -187,11 +190,45 @@ impl PeerConnection {
         }
     }
 
+    pub fn set_on_signaling_state_change(
+        &self,
+        cb: Option<impl Fn(SignalingState) + Send + Sync + 'static>,
+    ) {
+        let pc = self.pc.clone();
+        let cb = cb.map(|cb| {
+            wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(move |_ev: web_sys::Event| {
+                cb(pc.signaling_state());
+            })
+        });
+        self.pc
+            .set_onsignalingstatechange(cb.as_ref().map(|cb| cb.as_ref().unchecked_ref()));
+        if let Some(cb) = cb {
+            cb.forget();
+        }
+    }
+
+    pub fn set_on_negotiation_needed(&self, cb: Option<impl Fn() + Send + Sync + 'static>) {
+        let cb = cb.map(|cb| {
+            wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(move |_ev: web_sys::Event| {
+                cb();
+            })
+        });
+        self.pc
+            .set_onnegotiationneeded(cb.as_ref().map(|cb| cb.as_ref().unchecked_ref()));
+        if let Some(cb) = cb {
+            cb.forget();
+        }
+    }
+
     pub fn set_on_data_channel(&self, cb: Option<impl Fn(DataChannel) + Send + Sync + 'static>) {
+        let pc = self.pc.clone();
         let cb = cb.map(|cb| {
             wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(
                 move |ev: web_sys::RtcDataChannelEvent| {
-                    cb(DataChannel { dc: ev.channel() });
+                    cb(DataChannel {
+                        dc: ev.channel(),
+                        pc: pc.clone(),
+                    });
                 },
             )
         });
@@ -216,11 +253,21 @@ impl PeerConnection {
         })
     }
 
-    pub async fn add_ice_candidate(&self, cand: Option<&str>) -> Result<(), crate::Error> {
+    pub async fn add_ice_candidate(
+        &self,
+        cand: Option<&str>,
+        mid: Option<&str>,
+    ) -> Result<(), crate::Error> {
         wasm_bindgen_futures::JsFuture::from(
             self.pc.add_ice_candidate_with_opt_rtc_ice_candidate(
                 cand.map(|cand| {
                     let raw = web_sys::RtcIceCandidateInit::new(cand);
+                    match mid {
+                        Some(mid) => raw.set_sdp_mid(Some(mid)),
+                        // Browsers reject candidates without either a mid or an m-line index, and a connection with
+                        // only data channels has a single m-line.
+                        None => raw.set_sdp_m_line_index(Some(0)),
+                    }
                     web_sys::RtcIceCandidate::new(&raw).unwrap()
                 })
                 .as_ref(),
@@ -239,6 +286,7 @@ impl Drop for PeerConnection {
 
 pub struct DataChannel {
     dc: web_sys::RtcDataChannel,
+    pc: web_sys::RtcPeerConnection,
 }
 
 unsafe impl Send for DataChannel {}
@@ -301,17 +349,22 @@ impl DataChannel {
         }
     }
 
-    pub fn set_on_message(&self, cb: Option<impl Fn(&[u8]) + Send + Sync + 'static>) {
+    pub fn set_on_message(&self, cb: Option<impl Fn(Message) + Send + Sync + 'static>) {
         let cb = cb.map(|cb| {
             wasm_bindgen::closure::Closure::<dyn FnMut(_)>::new(move |ev: web_sys::MessageEvent| {
-                let arr = match ev.data().dyn_into::<js_sys::ArrayBuffer>() {
+                let data = ev.data();
+                if let Some(s) = data.as_string() {
+                    cb(Message::Text(s));
+                    return;
+                }
+                let arr = match data.dyn_into::<js_sys::ArrayBuffer>() {
                     Ok(arr) => arr,
                     Err(e) => {
                         log::error!("unsupported message: {:?}", e);
                         return;
                     }
                 };
-                cb(js_sys::Uint8Array::new(&arr).to_vec().as_slice());
+                cb(Message::Binary(js_sys::Uint8Array::new(&arr).to_vec()));
             })
         });
         self.dc
@@ -329,6 +382,51 @@ impl DataChannel {
         self.dc.buffered_amount()
     }
 
+    pub fn label(&self) -> String {
+        self.dc.label()
+    }
+
+    pub fn protocol(&self) -> String {
+        js_sys::Reflect::get(&self.dc, &"protocol".into())
+            .ok()
+            .and_then(|v| v.as_string())
+            .unwrap_or_default()
+    }
+
+    pub fn id(&self) -> Option<u16> {
+        self.dc.id()
+    }
+
+    pub fn ready_state(&self) -> DataChannelState {
+        self.dc.ready_state()
+    }
+
+    pub fn ordered(&self) -> bool {
+        js_sys::Reflect::get(&self.dc, &"ordered".into())
+            .ok()
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true)
+    }
+
+    pub fn max_packet_life_time(&self) -> Option<u16> {
+        self.dc.max_packet_life_time()
+    }
+
+    pub fn max_retransmits(&self) -> Option<u16> {
+        self.dc.max_retransmits()
+    }
+
+    pub fn max_message_size(&self) -> Option<u32> {
+        let sctp = js_sys::Reflect::get(&self.pc, &"sctp".into()).ok()?;
+        if sctp.is_null() || sctp.is_undefined() {
+            return None;
+        }
+        js_sys::Reflect::get(&sctp, &"maxMessageSize".into())
+            .ok()?
+            .as_f64()
+            .map(|v| v as u32)
+    }
+
     pub fn close(&self) {
         self.dc.close();
     }
@@ -337,6 +435,11 @@ impl DataChannel {
         self.dc.send_with_u8_array(buf)?;
         Ok(())
     }
+
+    pub fn send_text(&self, s: &str) -> Result<(), Error> {
+        self.dc.send_with_str(s)?;
+        Ok(())
+    }
 }
 
 impl Drop for DataChannel {
@@ -345,6 +448,12 @@ impl Drop for DataChannel {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
 pub struct DataChannelOptions {
     pub ordered: bool,
     pub max_packet_life_time: Option<u16>,
@@ -448,13 +557,13 @@ mod test {
         }));
 
         let (tx1, mut rx1) = futures::channel::mpsc::unbounded();
-        dc1.set_on_message(Some(move |msg: &[u8]| {
-            tx1.unbounded_send(msg.to_vec()).unwrap();
+        dc1.set_on_message(Some(move |msg: Message| {
+            tx1.unbounded_send(msg).unwrap();
         }));
 
         let (tx2, mut rx2) = futures::channel::mpsc::unbounded();
-        dc2.set_on_message(Some(move |msg: &[u8]| {
-            tx2.unbounded_send(msg.to_vec()).unwrap();
+        dc2.set_on_message(Some(move |msg: Message| {
+            tx2.unbounded_send(msg).unwrap();
         }));
 
         pc2.set_remote_description(&pc1.local_description().unwrap())
@@ -474,9 +583,21 @@ mod test {
         dc2_open.notified().await;
 
         dc1.send(b"hello world!").unwrap();
-        assert_eq!(rx2.next().await.unwrap(), b"hello world!");
+        assert_eq!(
+            rx2.next().await.unwrap(),
+            Message::Binary(b"hello world!".to_vec())
+        );
 
         dc2.send(b"goodbye world!").unwrap();
-        assert_eq!(rx1.next().await.unwrap(), b"goodbye world!");
+        assert_eq!(
+            rx1.next().await.unwrap(),
+            Message::Binary(b"goodbye world!".to_vec())
+        );
+
+        dc1.send_text("hello text!").unwrap();
+        assert_eq!(
+            rx2.next().await.unwrap(),
+            Message::Text("hello text!".to_string())
+        );
     }
 }